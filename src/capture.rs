@@ -0,0 +1,186 @@
+//! Live MIDI capture: record incoming `NoteOn`/`NoteOff` pairs into a
+//! `StagedProgram` in real time, quantized against the transport.
+
+use crate::{schedule, ProgramNote, StagedProgram, MAX_NOTES};
+
+/// Input quantize grid applied to captured note boundaries.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QuantizeGrid {
+    Off,
+    Quarter,
+    Eighth,
+    Sixteenth,
+}
+
+impl QuantizeGrid {
+    fn beats(self) -> Option<f64> {
+        match self {
+            QuantizeGrid::Off => None,
+            QuantizeGrid::Quarter => Some(1.0),
+            QuantizeGrid::Eighth => Some(0.5),
+            QuantizeGrid::Sixteenth => Some(0.25),
+        }
+    }
+}
+
+/// Snap `beat` to the nearest multiple of `grid`'s division, or leave it
+/// untouched if the grid is `Off`.
+pub fn snap(beat: f64, grid: QuantizeGrid) -> f64 {
+    match grid.beats() {
+        Some(g) if g > 0.0 => (beat / g).round() * g,
+        _ => beat,
+    }
+}
+
+/// Count-in length before captured notes are actually recorded: a fixed
+/// four quarter-note beats, which is one bar only in 4/4; the program's
+/// meter is not consulted.
+pub const COUNT_IN_BEATS: f64 = 4.0;
+
+/// Tracks in-flight recording: which pitches have a pending NoteOn
+/// (start beat + velocity, mirroring `ActiveNotes::end_beats`), and when
+/// recording was armed so the count-in can be enforced.
+#[derive(Clone)]
+pub struct RecordState {
+    pending: [Option<(f64, f32)>; 128],
+    armed_at_beat: Option<f64>,
+}
+
+impl Default for RecordState {
+    fn default() -> Self {
+        Self {
+            pending: [None; 128],
+            armed_at_beat: None,
+        }
+    }
+}
+
+impl RecordState {
+    /// Arm recording at the given transport beat (a no-op if already armed).
+    pub fn arm(&mut self, at_beat: f64) {
+        if self.armed_at_beat.is_none() {
+            self.armed_at_beat = Some(at_beat);
+        }
+    }
+
+    /// Disarm recording and discard any pending (unmatched) note-ons.
+    pub fn disarm(&mut self) {
+        self.armed_at_beat = None;
+        self.pending = [None; 128];
+    }
+
+    /// Whether `current_beat` is past the count-in and notes should capture.
+    pub fn is_capturing(&self, current_beat: f64) -> bool {
+        self.armed_at_beat
+            .map(|start| current_beat >= start + COUNT_IN_BEATS)
+            .unwrap_or(false)
+    }
+}
+
+/// Record a NoteOn: remember the (quantized) start beat and velocity as a
+/// pending note for this pitch.
+pub fn note_on(record: &mut RecordState, pitch: u8, velocity: f32, beat: f64, grid: QuantizeGrid) {
+    record.pending[pitch as usize] = Some((snap(beat, grid), velocity));
+}
+
+/// Record a NoteOff: finalize the pending note (if any) for this pitch and
+/// append it to `program`, respecting `MAX_NOTES`. Updates `length_bars` to
+/// cover the newly captured note.
+pub fn note_off(record: &mut RecordState, program: &mut StagedProgram, pitch: u8, beat: f64, grid: QuantizeGrid) {
+    let Some((start_beat, velocity)) = record.pending[pitch as usize].take() else {
+        return;
+    };
+
+    let end_beat = snap(beat, grid).max(start_beat + 1.0 / 64.0);
+    let length_beats = end_beat - start_beat;
+
+    if program.note_count < MAX_NOTES {
+        program.notes[program.note_count] = ProgramNote {
+            pitch,
+            velocity,
+            start_beat,
+            length_beats,
+            active: true,
+            channel: 0,
+            expression: None,
+        };
+        program.note_count += 1;
+    }
+
+    let beats_per_bar = schedule::beats_per_bar(
+        program.time_sig_numerator as i32,
+        program.time_sig_denominator as i32,
+    );
+    let bars_needed = (end_beat / beats_per_bar).ceil().max(1.0) as u32;
+    program.length_bars = program.length_bars.max(bars_needed.next_power_of_two() as f64);
+    program.length_beats = program.length_bars * beats_per_bar;
+    program.loaded = true;
+    program.version = program.version.wrapping_add(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_rounds_to_nearest_grid_multiple() {
+        assert_eq!(snap(1.3, QuantizeGrid::Quarter), 1.0);
+        assert_eq!(snap(1.8, QuantizeGrid::Quarter), 2.0);
+        assert_eq!(snap(1.3, QuantizeGrid::Off), 1.3);
+        assert_eq!(snap(0.2, QuantizeGrid::Sixteenth), 0.25);
+    }
+
+    #[test]
+    fn is_capturing_respects_count_in() {
+        let mut record = RecordState::default();
+        assert!(!record.is_capturing(0.0));
+        record.arm(0.0);
+        assert!(!record.is_capturing(COUNT_IN_BEATS - 0.01));
+        assert!(record.is_capturing(COUNT_IN_BEATS));
+        assert!(record.is_capturing(COUNT_IN_BEATS + 1.0));
+    }
+
+    #[test]
+    fn arm_is_idempotent_once_set() {
+        let mut record = RecordState::default();
+        record.arm(2.0);
+        record.arm(100.0);
+        assert!(record.is_capturing(6.0));
+        assert!(!record.is_capturing(5.0));
+    }
+
+    #[test]
+    fn note_on_off_appends_a_captured_note() {
+        let mut record = RecordState::default();
+        let mut program = StagedProgram::default();
+        note_on(&mut record, 60, 0.9, 1.0, QuantizeGrid::Off);
+        note_off(&mut record, &mut program, 60, 2.0, QuantizeGrid::Off);
+
+        assert_eq!(program.note_count, 1);
+        let note = program.notes[0];
+        assert_eq!(note.pitch, 60);
+        assert!((note.velocity - 0.9).abs() < 1e-6);
+        assert_eq!(note.start_beat, 1.0);
+        assert_eq!(note.length_beats, 1.0);
+        assert!(program.loaded);
+    }
+
+    #[test]
+    fn note_off_without_pending_note_on_is_a_no_op() {
+        let mut record = RecordState::default();
+        let mut program = StagedProgram::default();
+        note_off(&mut record, &mut program, 60, 2.0, QuantizeGrid::Off);
+        assert_eq!(program.note_count, 0);
+    }
+
+    #[test]
+    fn disarm_discards_pending_notes() {
+        let mut record = RecordState::default();
+        let mut program = StagedProgram::default();
+        record.arm(0.0);
+        note_on(&mut record, 60, 0.9, 1.0, QuantizeGrid::Off);
+        record.disarm();
+        note_off(&mut record, &mut program, 60, 2.0, QuantizeGrid::Off);
+        assert_eq!(program.note_count, 0);
+    }
+}
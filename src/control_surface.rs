@@ -0,0 +1,203 @@
+//! Optional MIDI control-surface mode: maps an 8x8 pad grid (note-on
+//! messages in a configurable base-note range) onto the staged program's
+//! step grid, so a grid controller can play the role of the piano-roll
+//! mouse editor. Pad columns are steps across `program.length_beats`,
+//! rows are a fixed set of pitches; pressing a pad toggles that cell.
+//!
+//! Three extra notes above the 8x8 grid are reserved for transport LEDs
+//! (play / stop / loop). They are display-only: this plugin has no way to
+//! request host transport changes (nih-plug's `Transport` is read-only, and
+//! `SysExMessage = ()` rules out the vendor SysEx most grid controllers use
+//! for transport control too), so pressing them is a no-op - only their LED
+//! state is kept in sync with the host.
+//!
+//! LED feedback reuses the contrast convention from `render_live_tab`
+//! (bright for lit, dark for empty) via plain note-on velocity, since a
+//! true per-pad RGB protocol would also require SysEx this plugin doesn't
+//! support.
+
+use crate::{ProgramNote, StagedProgram, MAX_NOTES};
+
+/// Grid is `GRID_SIZE` columns (steps) by `GRID_SIZE` rows (pitches).
+pub const GRID_SIZE: usize = 8;
+/// Number of step pads (`GRID_SIZE` * `GRID_SIZE`).
+pub const STEP_PADS: usize = GRID_SIZE * GRID_SIZE;
+/// Transport LED pad offsets, past the step grid.
+const PAD_PLAY: usize = STEP_PADS;
+const PAD_STOP: usize = STEP_PADS + 1;
+const PAD_LOOP: usize = STEP_PADS + 2;
+/// Total pads this mode listens on/lights, starting at `base_note`.
+pub const TOTAL_PADS: usize = STEP_PADS + 3;
+
+/// User-facing control-surface settings, edited from the Live tab.
+#[derive(Clone, Copy)]
+pub struct ControlSurfaceConfig {
+    pub enabled: bool,
+    /// MIDI note of pad 0 (column 0, row 0); pads occupy `base_note..base_note + TOTAL_PADS`.
+    pub base_note: u8,
+    /// Pitch represented by each grid row, bottom to top.
+    pub row_pitches: [u8; GRID_SIZE],
+}
+
+impl Default for ControlSurfaceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base_note: 36, // C2, the common Launchpad-style grid base
+            row_pitches: [36, 38, 40, 41, 43, 45, 47, 48],
+        }
+    }
+}
+
+/// Persistent control-surface state, stored in `SharedState`.
+pub struct ControlSurfaceState {
+    pub config: ControlSurfaceConfig,
+    /// Last velocity sent per pad, so LED feedback only re-sends on change.
+    last_led_velocity: [f32; TOTAL_PADS],
+    /// Reused across `led_updates` calls - cleared and refilled each block
+    /// rather than allocated fresh, same as `wasm_scratch`/the pitch-track
+    /// ring buffers: this runs from `process()` whenever control-surface
+    /// mode is enabled, and the audio thread never allocates.
+    led_update_buf: Vec<(u8, f32)>,
+}
+
+impl Default for ControlSurfaceState {
+    fn default() -> Self {
+        Self {
+            config: ControlSurfaceConfig::default(),
+            last_led_velocity: [-1.0; TOTAL_PADS], // force a full refresh on first block
+            led_update_buf: Vec::with_capacity(TOTAL_PADS),
+        }
+    }
+}
+
+/// One grid step's length in beats, spanning the program evenly across `GRID_SIZE` columns.
+fn step_beats(program: &StagedProgram) -> f64 {
+    (program.length_beats / GRID_SIZE as f64).max(1.0 / 16.0)
+}
+
+/// Find the pad index (step pad or transport pad, if any) a pad note falls on.
+fn pad_index(config: &ControlSurfaceConfig, note: u8) -> Option<usize> {
+    let offset = note.checked_sub(config.base_note)?;
+    (offset as usize < TOTAL_PADS).then_some(offset as usize)
+}
+
+/// Handle an incoming pad NoteOn. Step pads toggle the corresponding
+/// program cell; transport pads are ignored (see module docs).
+pub fn handle_pad_note_on(program: &mut StagedProgram, config: &ControlSurfaceConfig, note: u8) {
+    let Some(pad) = pad_index(config, note) else { return };
+    if pad >= STEP_PADS {
+        return;
+    }
+    toggle_cell(program, config, pad % GRID_SIZE, pad / GRID_SIZE);
+}
+
+/// Toggle the note at (`col`, `row`): delete it if one already occupies the
+/// cell, otherwise insert one `step_beats` long at `config.row_pitches[row]`.
+fn toggle_cell(program: &mut StagedProgram, config: &ControlSurfaceConfig, col: usize, row: usize) {
+    let step = step_beats(program);
+    let start_beat = col as f64 * step;
+    let pitch = config.row_pitches[row];
+
+    let existing = (0..program.note_count).find(|&i| {
+        let n = program.notes[i];
+        n.active && n.pitch == pitch && (n.start_beat - start_beat).abs() < step * 0.5
+    });
+
+    if let Some(i) = existing {
+        program.notes[i].active = false;
+    } else {
+        let slot = (0..program.note_count)
+            .find(|&i| !program.notes[i].active)
+            .or_else(|| {
+                if program.note_count < MAX_NOTES {
+                    let i = program.note_count;
+                    program.note_count += 1;
+                    Some(i)
+                } else {
+                    None
+                }
+            });
+        if let Some(i) = slot {
+            program.notes[i] = ProgramNote {
+                pitch,
+                velocity: 0.9,
+                start_beat,
+                length_beats: step,
+                active: true,
+                channel: 0,
+                expression: None,
+            };
+        }
+    }
+
+    program.version = program.version.wrapping_add(1);
+    program.loaded = true;
+}
+
+/// Compute this block's LED velocities and return only the `(note,
+/// velocity)` pairs that changed since the last call, so enabled controllers
+/// aren't flooded with redundant note-ons every block. The returned slice
+/// borrows `state.led_update_buf`, reused across calls rather than
+/// allocated fresh - see that field's doc comment.
+pub fn led_updates(
+    state: &mut ControlSurfaceState,
+    program: &StagedProgram,
+    playhead_beat: Option<f64>,
+    track_color: Option<(u8, u8, u8)>,
+    transport_playing: bool,
+    loop_active: bool,
+) -> &[(u8, f32)] {
+    let step = step_beats(program);
+    let length = program.length_beats.max(step);
+    let playhead_col = playhead_beat.map(|b| (b.rem_euclid(length) / step) as usize % GRID_SIZE);
+
+    // Mirrors render_live_tab's contrast check: a bright track color gets a
+    // full-velocity LED, a dark/missing one still shows at a visible level.
+    let lit_velocity = match track_color {
+        Some((r, g, b)) if (r as u32 + g as u32 + b as u32) > 384 => 1.0,
+        Some(_) => 0.7,
+        None => 0.7,
+    };
+
+    let config = state.config;
+    state.led_update_buf.clear();
+
+    for row in 0..GRID_SIZE {
+        let pitch = config.row_pitches[row];
+        for col in 0..GRID_SIZE {
+            let pad = row * GRID_SIZE + col;
+            let active = (0..program.note_count).any(|i| {
+                let n = program.notes[i];
+                n.active && n.pitch == pitch && (n.start_beat - col as f64 * step).abs() < step * 0.5
+            });
+            let is_playhead_col = playhead_col == Some(col);
+            let velocity = if active && is_playhead_col {
+                1.0
+            } else if active {
+                lit_velocity
+            } else if is_playhead_col {
+                0.15
+            } else {
+                0.0
+            };
+            push_led_update(state, config, pad, velocity);
+        }
+    }
+
+    push_led_update(state, config, PAD_PLAY, if transport_playing { 1.0 } else { 0.0 });
+    push_led_update(state, config, PAD_STOP, if transport_playing { 0.0 } else { 1.0 });
+    push_led_update(state, config, PAD_LOOP, if loop_active { 1.0 } else { 0.0 });
+
+    &state.led_update_buf
+}
+
+/// Push `(note, velocity)` for `pad` onto `state.led_update_buf` if it
+/// changed since the last call, updating `last_led_velocity` either way.
+fn push_led_update(state: &mut ControlSurfaceState, config: ControlSurfaceConfig, pad: usize, velocity: f32) {
+    if (state.last_led_velocity[pad] - velocity).abs() > f32::EPSILON {
+        state.last_led_velocity[pad] = velocity;
+        let note = (config.base_note as u32 + pad as u32).min(127) as u8;
+        state.led_update_buf.push((note, velocity));
+    }
+}
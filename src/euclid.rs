@@ -0,0 +1,190 @@
+//! Euclidean rhythm generation (Bjorklund's algorithm).
+//!
+//! Distributes `k` pulses as evenly as possible over `n` steps, the same
+//! construction used by the Bjorklund/Euclidean-rhythm family of algorithms
+//! popularized in drum-machine firmware.
+
+use crate::{schedule, ProgramNote, StagedProgram, MAX_NOTES};
+
+/// One voice to be rendered into a `StagedProgram` by [`render_voices`].
+#[derive(Clone, Copy)]
+pub struct EuclidVoice {
+    /// Number of onsets ("pulses") distributed over `steps`.
+    pub pulses: u32,
+    /// Total steps in the pattern.
+    pub steps: u32,
+    /// Cyclic rotation applied to the onset pattern.
+    pub rotation: u32,
+    /// MIDI pitch triggered on each onset.
+    pub pitch: u8,
+    /// Velocity (0.0-1.0) for each onset.
+    pub velocity: f32,
+    /// Length in beats of each step (also the note length).
+    pub step_length_beats: f64,
+}
+
+impl Default for EuclidVoice {
+    fn default() -> Self {
+        Self {
+            pulses: 4,
+            steps: 16,
+            rotation: 0,
+            pitch: 48,
+            velocity: 0.8,
+            step_length_beats: 0.25,
+        }
+    }
+}
+
+/// Compute the boolean onset pattern for `pulses` distributed over `steps`
+/// using Bjorklund's algorithm: start with `pulses` groups of `[true]` and
+/// `steps - pulses` groups of `[false]`, then repeatedly append each
+/// remainder group onto a head group until the shorter side is exhausted.
+pub fn bjorklund(pulses: u32, steps: u32) -> Vec<bool> {
+    if steps == 0 {
+        return Vec::new();
+    }
+    let pulses = pulses.min(steps);
+    if pulses == 0 {
+        return vec![false; steps as usize];
+    }
+    if pulses == steps {
+        return vec![true; steps as usize];
+    }
+
+    let mut head: Vec<Vec<bool>> = (0..pulses).map(|_| vec![true]).collect();
+    let mut remainder: Vec<Vec<bool>> = (0..(steps - pulses)).map(|_| vec![false]).collect();
+
+    while remainder.len() > 1 {
+        let pair_count = head.len().min(remainder.len());
+        let mut new_head = Vec::with_capacity(pair_count);
+        for i in 0..pair_count {
+            let mut group = head[i].clone();
+            group.extend(remainder[i].clone());
+            new_head.push(group);
+        }
+        let new_remainder = if head.len() > remainder.len() {
+            head[pair_count..].to_vec()
+        } else {
+            remainder[pair_count..].to_vec()
+        };
+        head = new_head;
+        remainder = new_remainder;
+    }
+
+    head.into_iter().chain(remainder).flatten().collect()
+}
+
+/// Cyclically rotate `pattern` left by `rotation` steps.
+fn rotate(pattern: &[bool], rotation: u32) -> Vec<bool> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+    let r = (rotation as usize) % pattern.len();
+    pattern[r..].iter().chain(pattern[..r].iter()).copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classic_patterns() {
+        // E(3, 8), the tresillo, is the textbook example for this algorithm.
+        assert_eq!(
+            bjorklund(3, 8),
+            vec![true, false, false, true, false, false, true, false]
+        );
+        // E(4, 16) is four evenly spaced pulses.
+        assert_eq!(
+            bjorklund(4, 16),
+            vec![
+                true, false, false, false, true, false, false, false, true, false, false, false,
+                true, false, false, false
+            ]
+        );
+    }
+
+    #[test]
+    fn pulse_count_matches_steps() {
+        for steps in 1..=32u32 {
+            for pulses in 0..=steps {
+                let pattern = bjorklund(pulses, steps);
+                assert_eq!(pattern.len(), steps as usize);
+                assert_eq!(pattern.iter().filter(|&&b| b).count(), pulses as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn edge_cases() {
+        assert_eq!(bjorklund(0, 0), Vec::<bool>::new());
+        assert_eq!(bjorklund(0, 4), vec![false; 4]);
+        assert_eq!(bjorklund(4, 4), vec![true; 4]);
+        // More pulses than steps clamps to one onset per step.
+        assert_eq!(bjorklund(9, 4), vec![true; 4]);
+    }
+
+    #[test]
+    fn rotate_wraps_left() {
+        let pattern = vec![true, false, false, true];
+        assert_eq!(rotate(&pattern, 1), vec![false, false, true, true]);
+        assert_eq!(rotate(&pattern, 4), pattern);
+        assert_eq!(rotate(&[], 3), Vec::<bool>::new());
+    }
+}
+
+/// Render one voice's onset pattern into `ProgramNote`s.
+pub fn voice_notes(voice: &EuclidVoice) -> Vec<ProgramNote> {
+    let pattern = rotate(&bjorklund(voice.pulses, voice.steps), voice.rotation);
+    pattern
+        .iter()
+        .enumerate()
+        .filter(|(_, &onset)| onset)
+        .map(|(i, _)| ProgramNote {
+            pitch: voice.pitch,
+            velocity: voice.velocity,
+            start_beat: i as f64 * voice.step_length_beats,
+            length_beats: voice.step_length_beats,
+            active: true,
+            channel: 0,
+            expression: None,
+        })
+        .collect()
+}
+
+/// Stack several Euclidean voices into `program`, replacing its notes.
+/// The program length is set to cover the longest voice's pattern, rounded
+/// up to a power-of-two number of whole bars under the program's own time
+/// signature.
+pub fn render_voices(program: &mut StagedProgram, voices: &[EuclidVoice]) {
+    let mut notes: Vec<ProgramNote> = Vec::new();
+    let mut max_beat = 0.0f64;
+
+    for voice in voices {
+        for note in voice_notes(voice) {
+            max_beat = max_beat.max(note.start_beat + note.length_beats);
+            if notes.len() < MAX_NOTES {
+                notes.push(note);
+            }
+        }
+    }
+
+    program.note_count = notes.len();
+    for (i, note) in notes.into_iter().enumerate() {
+        program.notes[i] = note;
+    }
+    for i in program.note_count..MAX_NOTES {
+        program.notes[i].active = false;
+    }
+
+    let beats_per_bar = schedule::beats_per_bar(
+        program.time_sig_numerator as i32,
+        program.time_sig_denominator as i32,
+    );
+    let bars_needed = (max_beat / beats_per_bar).ceil().max(1.0) as u32;
+    program.length_bars = bars_needed.next_power_of_two() as f64;
+    program.length_beats = program.length_bars * beats_per_bar;
+    program.version += 1;
+    program.loaded = true;
+}
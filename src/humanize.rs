@@ -0,0 +1,172 @@
+//! Humanized performance layer: swing and per-note timing/velocity variation
+//! applied at trigger time, without mutating the stored `ProgramNote`s.
+//!
+//! Jitter is derived from a deterministic hash of the note's index, so the
+//! same note in the same program always humanizes the same way across
+//! loop iterations (no audible "random per cycle" flutter).
+
+use crate::ProgramNote;
+
+/// Tunable knobs for the performance layer, stored in `SharedState`.
+#[derive(Clone, Copy)]
+pub struct HumanizeParams {
+    /// 0.0 (no swing) - 1.0 (full swing) applied to off-beat eighths.
+    pub swing_amount: f32,
+    /// Maximum delay, in beats, a fully-swung off-beat eighth can receive.
+    pub max_swing_beats: f64,
+    /// Bounded timing jitter, in beats (0.0 disables).
+    pub timing_jitter_beats: f64,
+    /// Bounded velocity jitter, as a fraction of velocity (0.0 disables).
+    pub velocity_jitter: f32,
+}
+
+impl Default for HumanizeParams {
+    fn default() -> Self {
+        Self {
+            swing_amount: 0.0,
+            max_swing_beats: 0.08,
+            timing_jitter_beats: 0.0,
+            velocity_jitter: 0.0,
+        }
+    }
+}
+
+/// splitmix64 step - cheap, deterministic, decent avalanche for seeding.
+fn hash_u64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Uniform value in [0, 1) derived from `(seed, stream)`.
+fn uniform01(seed: u64, stream: u64) -> f64 {
+    let h = hash_u64(seed.wrapping_mul(0x2545_F491_4F6C_DD1D).wrapping_add(stream));
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Irwin-Hall approximation of a standard Gaussian: sum of 4 independent
+/// uniforms, recentered to mean 0 (range roughly [-2, 2]).
+fn gaussian_ish(seed: u64, stream: u64) -> f64 {
+    let mut sum = 0.0;
+    for i in 0..4u64 {
+        sum += uniform01(seed, stream.wrapping_add(i));
+    }
+    sum - 2.0
+}
+
+/// Given a `note` and its index in the program, yield the
+/// `(effective_start_beat, effective_velocity)` to use when triggering it,
+/// with swing and jitter applied. Clamped so the note never moves before
+/// the beat boundary it started in, nor exceeds velocity 1.0.
+pub fn apply(note: &ProgramNote, note_index: usize, params: &HumanizeParams) -> (f64, f32) {
+    let mut effective_start = note.start_beat;
+
+    // Swing: delay off-beat eighths (odd half-beat index) by up to max_swing_beats.
+    let half_beat_index = (note.start_beat * 2.0).round() as i64;
+    if params.swing_amount > 0.0 && half_beat_index % 2 != 0 {
+        effective_start += params.swing_amount as f64 * params.max_swing_beats;
+    }
+
+    // Timing humanization: deterministic bounded jitter, seeded per note index.
+    if params.timing_jitter_beats > 0.0 {
+        let jitter = gaussian_ish(note_index as u64, 0) * params.timing_jitter_beats;
+        effective_start += jitter;
+    }
+
+    // Never move a note before the beat boundary it started in.
+    let prev_beat_boundary = note.start_beat.floor();
+    effective_start = effective_start.max(prev_beat_boundary);
+
+    let mut effective_velocity = note.velocity;
+    if params.velocity_jitter > 0.0 {
+        let factor = 1.0 + (gaussian_ish(note_index as u64, 100) as f32) * params.velocity_jitter;
+        effective_velocity = (effective_velocity * factor).clamp(0.0, 1.0);
+    }
+
+    (effective_start, effective_velocity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(start_beat: f64) -> ProgramNote {
+        ProgramNote {
+            pitch: 60,
+            velocity: 0.5,
+            start_beat,
+            length_beats: 1.0,
+            active: true,
+            channel: 0,
+            expression: None,
+        }
+    }
+
+    #[test]
+    fn zeroed_params_are_a_no_op() {
+        let params = HumanizeParams::default();
+        let n = note(1.5);
+        assert_eq!(apply(&n, 0, &params), (1.5, 0.5));
+    }
+
+    #[test]
+    fn swing_delays_offbeat_eighths_only() {
+        let params = HumanizeParams {
+            swing_amount: 1.0,
+            max_swing_beats: 0.1,
+            timing_jitter_beats: 0.0,
+            velocity_jitter: 0.0,
+        };
+        // On-beat eighth (half_beat_index even): unaffected.
+        let (on_beat, _) = apply(&note(1.0), 0, &params);
+        assert_eq!(on_beat, 1.0);
+        // Off-beat eighth (half_beat_index odd): delayed by the full swing amount.
+        let (off_beat, _) = apply(&note(1.5), 0, &params);
+        assert!((off_beat - 1.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn timing_jitter_is_deterministic_and_bounded() {
+        let params = HumanizeParams {
+            swing_amount: 0.0,
+            max_swing_beats: 0.0,
+            timing_jitter_beats: 0.05,
+            velocity_jitter: 0.0,
+        };
+        let n = note(4.0);
+        let (first, _) = apply(&n, 7, &params);
+        let (second, _) = apply(&n, 7, &params);
+        assert_eq!(first, second, "same note index must humanize identically every call");
+        assert!(first >= 4.0 - 0.2 && first <= 4.0 + 0.2);
+    }
+
+    #[test]
+    fn start_never_moves_before_its_beat_boundary() {
+        let params = HumanizeParams {
+            swing_amount: 0.0,
+            max_swing_beats: 0.0,
+            timing_jitter_beats: 1.0,
+            velocity_jitter: 0.0,
+        };
+        for note_index in 0..64usize {
+            let n = note(2.25);
+            let (effective_start, _) = apply(&n, note_index, &params);
+            assert!(effective_start >= 2.0);
+        }
+    }
+
+    #[test]
+    fn velocity_jitter_stays_clamped_to_unit_range() {
+        let params = HumanizeParams {
+            swing_amount: 0.0,
+            max_swing_beats: 0.0,
+            timing_jitter_beats: 0.0,
+            velocity_jitter: 5.0,
+        };
+        for note_index in 0..64usize {
+            let (_, velocity) = apply(&note(0.0), note_index, &params);
+            assert!((0.0..=1.0).contains(&velocity));
+        }
+    }
+}
@@ -2,9 +2,28 @@ use atomic_refcell::AtomicRefCell;
 use nih_plug::prelude::*;
 use nih_plug_egui::{create_egui_editor, egui, EguiState};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Instant, SystemTime};
+
+mod capture;
+mod control_surface;
+mod euclid;
+mod humanize;
+mod load_meter;
+mod metronome;
+mod persistence;
+mod piano_roll;
+mod pitch_track;
+mod schedule;
+mod smf;
+mod sync;
+mod transform;
+mod transport_ring;
+mod triple_buffer;
+// `pub` so `tests/wasm_dsp_gain.rs` can drive the guest ABI directly rather
+// than through the full CLAP activation path - see that test for why.
+pub mod wasm_dsp;
 
 /// Staging directory where gilligan.py writes program files
 const STAGING_DIR: &str = "/tmp/skipper";
@@ -12,6 +31,40 @@ const STAGING_DIR: &str = "/tmp/skipper";
 /// Global counter for unique plugin instance IDs
 static INSTANCE_COUNTER: AtomicU32 = AtomicU32::new(0);
 
+/// Latched once any plugin callback panics, so every later callback
+/// short-circuits to its safe default too instead of running more plugin
+/// code against state a panic may have left half-updated.
+static PANIC_POISONED: AtomicBool = AtomicBool::new(false);
+
+/// Run `f`, isolating a panic inside it from unwinding across the CLAP C
+/// ABI boundary - undefined behavior, since `extern "C"` functions can't
+/// unwind through them. Returns `default` (a safe value: `false` for
+/// bool-returning callbacks, `None` for pointer-returning ones, an error
+/// status for `process`) if `f` panics or the plugin is already poisoned
+/// from an earlier panic.
+///
+/// In test builds this calls `f` directly with no isolation, so a genuine
+/// assertion failure inside a callback still panics and fails the test
+/// instead of being swallowed (see `test_plugin_receives_track_name`).
+#[cfg(not(test))]
+fn handle_panic<T>(default: T, f: impl FnOnce() -> T) -> T {
+    if PANIC_POISONED.load(Ordering::Acquire) {
+        return default;
+    }
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(_) => {
+            PANIC_POISONED.store(true, Ordering::Release);
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+fn handle_panic<T>(_default: T, f: impl FnOnce() -> T) -> T {
+    f()
+}
+
 /// Which tab is currently selected
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -21,9 +74,113 @@ enum Tab {
     Info = 2,
 }
 
+/// How a newly loaded/switched program is scheduled to start, clip-launch
+/// style, instead of cutting in on whatever sample `process()` happens to
+/// be handling when the load arrives.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LaunchQuantization {
+    Immediate,
+    NextBeat,
+    NextBar,
+    Bars(u32),
+}
+
+impl Default for LaunchQuantization {
+    fn default() -> Self {
+        LaunchQuantization::Immediate
+    }
+}
+
+impl LaunchQuantization {
+    /// This mode's launch grid, in beats, for `time_sig_numerator/denominator`
+    /// - `None` for `Immediate`, which never waits for a boundary at all.
+    fn grid_beats(self, time_sig_numerator: i32, time_sig_denominator: i32) -> Option<f64> {
+        match self {
+            LaunchQuantization::Immediate => None,
+            LaunchQuantization::NextBeat => Some(1.0),
+            LaunchQuantization::NextBar => Some(schedule::beats_per_bar(time_sig_numerator, time_sig_denominator)),
+            LaunchQuantization::Bars(n) => {
+                Some(schedule::beats_per_bar(time_sig_numerator, time_sig_denominator) * n.max(1) as f64)
+            }
+        }
+    }
+}
+
+/// A program staged for a quantized launch, bundled with the quantization
+/// mode in effect at the moment it was armed - changing the setting
+/// afterward shouldn't retroactively reschedule a launch already in flight.
+#[derive(Clone)]
+struct PendingLaunch {
+    program: StagedProgram,
+    quantization: LaunchQuantization,
+}
+
+impl Default for PendingLaunch {
+    fn default() -> Self {
+        Self {
+            program: StagedProgram::default(),
+            quantization: LaunchQuantization::Immediate,
+        }
+    }
+}
+
 /// Maximum notes per program (pre-allocated to avoid audio thread allocs)
 const MAX_NOTES: usize = 256;
 
+/// Which continuous MIDI message a note's [`ExpressionCurve`] drives.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExpressionTarget {
+    PitchBend,
+    Pressure,
+    /// Channel volume, sent as CC7.
+    Volume,
+    /// Pan, sent as CC10.
+    Pan,
+}
+
+/// How an [`ExpressionCurve`] moves from its start value to its end value
+/// over the note's span.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Interpolation {
+    Linear,
+    /// Holds `start` while the note sounds; `end` is sent only when a block
+    /// begins at or past the note's end beat while the note is still held,
+    /// so no event is placed at the end beat itself.
+    Step,
+}
+
+/// A per-note continuous-expression lane (MPE-style pitch-bend, pressure, or
+/// CC volume/pan), sampled once per block at the note's current progress -
+/// see `emit_program_block`'s handling of `ProgramNote::expression`.
+#[derive(Clone, Copy)]
+struct ExpressionCurve {
+    target: ExpressionTarget,
+    start: f32,
+    end: f32,
+    interpolation: Interpolation,
+}
+
+/// Parse a note's `"expression"` JSON object (from Gilligan) into an
+/// [`ExpressionCurve`] - `None` if the shape doesn't match, so a malformed
+/// or absent field just leaves the note without one rather than failing the
+/// whole program load.
+fn parse_expression_json(json: &serde_json::Value) -> Option<ExpressionCurve> {
+    let target = match json.get("target").and_then(|v| v.as_str())? {
+        "pitchBend" => ExpressionTarget::PitchBend,
+        "pressure" => ExpressionTarget::Pressure,
+        "volume" => ExpressionTarget::Volume,
+        "pan" => ExpressionTarget::Pan,
+        _ => return None,
+    };
+    let start = json.get("start")?.as_f64()? as f32;
+    let end = json.get("end")?.as_f64()? as f32;
+    let interpolation = match json.get("interpolation").and_then(|v| v.as_str()) {
+        Some("step") => Interpolation::Step,
+        _ => Interpolation::Linear,
+    };
+    Some(ExpressionCurve { target, start, end, interpolation })
+}
+
 /// A single MIDI note in a program
 #[derive(Clone, Copy, Default)]
 struct ProgramNote {
@@ -32,6 +189,8 @@ struct ProgramNote {
     start_beat: f64,     // Start position in beats from program start
     length_beats: f64,   // Duration in beats
     active: bool,        // Is this slot in use?
+    channel: u8,         // MIDI channel 0-15, for multi-timbral/MPE targets
+    expression: Option<ExpressionCurve>, // Optional pitch-bend/pressure/volume/pan lane
 }
 
 /// Fixed-size string for program names (no heap allocation)
@@ -47,6 +206,8 @@ struct StagedProgram {
     note_count: usize,
     length_bars: f64,           // Program length in bars (power of 2)
     length_beats: f64,          // Cached: length_bars * beats_per_bar
+    time_sig_numerator: u8,     // Program's own meter, so it displays and
+    time_sig_denominator: u8,   // loops correctly before the host reports transport
     loaded: bool,               // Is a program loaded?
 }
 
@@ -60,12 +221,19 @@ impl Default for StagedProgram {
             note_count: 0,
             length_bars: 4.0,
             length_beats: 16.0, // 4 bars * 4 beats
+            time_sig_numerator: 4,
+            time_sig_denominator: 4,
             loaded: false,
         }
     }
 }
 
 impl StagedProgram {
+    /// Quarter-note beats per bar under this program's own time signature.
+    fn beats_per_bar(&self) -> f64 {
+        schedule::beats_per_bar(self.time_sig_numerator as i32, self.time_sig_denominator as i32)
+    }
+
     /// Set program name (copies into fixed buffer)
     fn set_name(&mut self, name: &str) {
         let bytes = name.as_bytes();
@@ -81,42 +249,52 @@ impl StagedProgram {
     }
 }
 
-/// Tracks which notes are currently playing (for note-off)
+/// Number of MIDI channels tracked per pitch, so same-pitch notes on
+/// different channels (MPE-style voices) each get their own bookkeeping
+/// instead of stomping on one another.
+const MIDI_CHANNELS: usize = 16;
+const ACTIVE_NOTE_SLOTS: usize = MIDI_CHANNELS * 128;
+
+/// Tracks which (channel, pitch) notes are currently playing (for note-off)
 #[derive(Clone)]
 struct ActiveNotes {
-    /// Bit flags for active notes (128 bits = 128 MIDI notes)
-    playing: [u64; 2],
+    /// Bit flags for active notes (2048 bits = 16 channels * 128 pitches)
+    playing: [u64; ACTIVE_NOTE_SLOTS / 64],
     /// End beat for each playing note
-    end_beats: [f64; 128],
+    end_beats: [f64; ACTIVE_NOTE_SLOTS],
 }
 
 impl Default for ActiveNotes {
     fn default() -> Self {
         Self {
-            playing: [0; 2],
-            end_beats: [0.0; 128],
+            playing: [0; ACTIVE_NOTE_SLOTS / 64],
+            end_beats: [0.0; ACTIVE_NOTE_SLOTS],
         }
     }
 }
 
 impl ActiveNotes {
-    fn is_playing(&self, pitch: u8) -> bool {
-        let idx = pitch as usize;
+    fn index(channel: u8, pitch: u8) -> usize {
+        (channel as usize % MIDI_CHANNELS) * 128 + pitch as usize
+    }
+
+    fn is_playing(&self, channel: u8, pitch: u8) -> bool {
+        let idx = Self::index(channel, pitch);
         let word = idx / 64;
         let bit = idx % 64;
         (self.playing[word] & (1u64 << bit)) != 0
     }
 
-    fn set_playing(&mut self, pitch: u8, end_beat: f64) {
-        let idx = pitch as usize;
+    fn set_playing(&mut self, channel: u8, pitch: u8, end_beat: f64) {
+        let idx = Self::index(channel, pitch);
         let word = idx / 64;
         let bit = idx % 64;
         self.playing[word] |= 1u64 << bit;
         self.end_beats[idx] = end_beat;
     }
 
-    fn clear_playing(&mut self, pitch: u8) {
-        let idx = pitch as usize;
+    fn clear_playing(&mut self, channel: u8, pitch: u8) {
+        let idx = Self::index(channel, pitch);
         let word = idx / 64;
         let bit = idx % 64;
         self.playing[word] &= !(1u64 << bit);
@@ -138,13 +316,21 @@ impl StagedProgram {
             .and_then(|v| v.as_u64())
             .unwrap_or(1) as u32;
 
+        // Get time signature (defaults to 4/4 if Gilligan doesn't send one)
+        self.time_sig_numerator = json.get("timeSigNumerator")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(4) as u8;
+        self.time_sig_denominator = json.get("timeSigDenominator")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(4) as u8;
+
         // Get length
         self.length_bars = json.get("lengthBars")
             .and_then(|v| v.as_f64())
             .unwrap_or(4.0);
         self.length_beats = json.get("lengthBeats")
             .and_then(|v| v.as_f64())
-            .unwrap_or(self.length_bars * 4.0);
+            .unwrap_or(self.length_bars * self.beats_per_bar());
 
         // Parse notes
         let notes = match json.get("notes") {
@@ -170,6 +356,10 @@ impl StagedProgram {
             let velocity = note_json.get("velocity")
                 .and_then(|v| v.as_f64())
                 .unwrap_or(0.8) as f32;
+            let channel = note_json.get("channel")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as u8;
+            let expression = note_json.get("expression").and_then(parse_expression_json);
 
             self.notes[self.note_count] = ProgramNote {
                 pitch,
@@ -177,6 +367,8 @@ impl StagedProgram {
                 length_beats,
                 velocity,
                 active: true,
+                channel,
+                expression,
             };
             self.note_count += 1;
         }
@@ -240,6 +432,8 @@ impl StagedProgram {
                 length_beats: *length,
                 velocity: *vel,
                 active: true,
+                channel: 0,
+                expression: None,
             };
             let bar = (*start as i32 / 4) + 1;
             let beat = (*start % 4.0) + 1.0;
@@ -311,6 +505,8 @@ impl StagedProgram {
                 length_beats: *length,
                 velocity: *vel,
                 active: true,
+                channel: 0,
+                expression: None,
             };
             let bar = (*start as i32 / 4) + 1;
             let beat = (*start % 4.0) + 1.0;
@@ -379,6 +575,8 @@ impl StagedProgram {
                     length_beats: *length,
                     velocity: *vel,
                     active: true,
+                    channel: 0,
+                    expression: None,
                 };
                 let bar = (*start as i32 / 4) + 1;
                 let beat = (*start % 4.0) + 1.0;
@@ -394,6 +592,8 @@ impl StagedProgram {
                     length_beats: *length,
                     velocity: *vel * 0.9,
                     active: true,
+                    channel: 0,
+                    expression: None,
                 };
                 nih_log!("  [{:2}] {} @ same time (fifth)",
                     self.note_count, Self::pitch_to_name(*fifth));
@@ -442,6 +642,8 @@ impl StagedProgram {
                 length_beats: 0.25,
                 velocity: 0.9,
                 active: true,
+                channel: 0,
+                expression: None,
             };
             idx += 1;
 
@@ -452,6 +654,8 @@ impl StagedProgram {
                 length_beats: 0.25,
                 velocity: 0.85,
                 active: true,
+                channel: 0,
+                expression: None,
             };
             idx += 1;
 
@@ -462,6 +666,8 @@ impl StagedProgram {
                 length_beats: 0.25,
                 velocity: 0.9,
                 active: true,
+                channel: 0,
+                expression: None,
             };
             idx += 1;
 
@@ -472,6 +678,8 @@ impl StagedProgram {
                 length_beats: 0.25,
                 velocity: 0.85,
                 active: true,
+                channel: 0,
+                expression: None,
             };
             idx += 1;
         }
@@ -531,6 +739,72 @@ struct TransportState {
     loop_end_beats: Option<f64>,
 }
 
+/// Snapshot of the host's track metadata (name, color, channel count),
+/// mirrored into `Skipper::track_info_buf` so the audio thread can read the
+/// latest copy lock-free (see `triple_buffer`) instead of going through
+/// `SharedState`'s `try_borrow`.
+///
+/// `clap_host_track_info::get` is a main-thread-only call (CLAP forbids
+/// calling host extensions from the audio thread), so the audio thread never
+/// makes that query itself. `nih_export_clap!` implements the real plugin-
+/// side `clap_plugin_track_info` extension (`changed()` included - see
+/// `tests/clap_track_info.rs::plugin_requeries_host_when_changed_is_called`
+/// for a test driving that callback directly) and folds the refreshed value
+/// into `ProcessContext`/`GuiContext`/`InitContext::track_info()` alike, all
+/// backed by the same cache rather than a fresh host call each time. This
+/// snapshot is refreshed from `initialize()`, every editor frame (see
+/// `Skipper::editor`), and every `process()` block (see `process_impl`) -
+/// the last of those is what keeps the audio-thread mirror from going stale
+/// after a host rename/recolor with no GUI open.
+#[derive(Clone, Default)]
+struct TrackInfoSnapshot {
+    name: Option<String>,
+    color: Option<(u8, u8, u8)>,
+    audio_channel_count: Option<u32>,
+}
+
+impl TrackInfoSnapshot {
+    fn from_track_info(track_info: &Option<Arc<TrackInfo>>) -> Self {
+        match track_info {
+            Some(track) => Self {
+                name: track.name.clone(),
+                color: track.color,
+                audio_channel_count: track.audio_channel_count,
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod track_info_snapshot_tests {
+    use super::*;
+
+    #[test]
+    fn from_track_info_mirrors_name_color_and_channels() {
+        let track = Arc::new(TrackInfo {
+            name: Some("Drums".to_string()),
+            color: Some((10, 20, 30)),
+            audio_channel_count: Some(2),
+            is_for_master: false,
+            is_for_return_track: false,
+            is_for_bus: false,
+        });
+        let snapshot = TrackInfoSnapshot::from_track_info(&Some(track));
+        assert_eq!(snapshot.name, Some("Drums".to_string()));
+        assert_eq!(snapshot.color, Some((10, 20, 30)));
+        assert_eq!(snapshot.audio_channel_count, Some(2));
+    }
+
+    #[test]
+    fn from_track_info_defaults_when_host_gave_nothing() {
+        let snapshot = TrackInfoSnapshot::from_track_info(&None);
+        assert_eq!(snapshot.name, None);
+        assert_eq!(snapshot.color, None);
+        assert_eq!(snapshot.audio_channel_count, None);
+    }
+}
+
 /// Shared state between plugin and GUI
 struct SharedState {
     host_info: Option<HostInfo>,
@@ -540,10 +814,45 @@ struct SharedState {
     buffer_size: u32,
     plugin_api: PluginApi,
     current_tab: Tab,
-    // Program playback state
+    // Program playback state (the editor's authoritative copy; the audio
+    // thread plays from its own triple-buffered mirror - see
+    // `Skipper::program_buf` - and never touches this field directly)
     program: StagedProgram,
-    active_notes: ActiveNotes,
-    last_program_beat: f64,  // Last beat position we processed (for note triggers)
+    // How the next program load/switch (SMF import, Gilligan push) should be
+    // scheduled - edited from the Program tab, read by the callers of
+    // `stage_program_launch`, which pass it in.
+    launch_quantization: LaunchQuantization,
+    // Euclidean generator voices, edited from the Program tab
+    euclid_voices: [euclid::EuclidVoice; 3],
+    // Pattern-transform controls, edited from the Program tab
+    echo_repeats: u32,
+    echo_offset_beats: f64,
+    echo_decay: f32,
+    scale_root: u8,
+    scale_is_minor: bool,
+    // Humanized performance layer (applied at trigger time, Live tab controls)
+    humanize: humanize::HumanizeParams,
+    // Live MIDI capture
+    recording: bool,
+    record_quantize: capture::QuantizeGrid,
+    record: capture::RecordState,
+    // Piano-roll editor UI state
+    piano_roll: piano_roll::PianoRollState,
+    // MIDI pad-grid control surface
+    control_surface: control_surface::ControlSurfaceState,
+    // Metronome click synth
+    metronome: metronome::MetronomeConfig,
+    click: metronome::ClickState,
+    // Audio-to-MIDI pitch tracking
+    pitch_track: pitch_track::PitchTrackConfig,
+    // User-supplied WASM DSP chain
+    wasm_dsp: wasm_dsp::WasmDspConfig,
+    // DSP-load meter
+    load_meter: load_meter::LoadMeter,
+    // Gilligan WebSocket sync status, mirrored from the background thread -
+    // see `sync::set_status`/`sync::mark_update`.
+    sync_status: sync::ConnectionStatus,
+    sync_last_update: Option<SystemTime>,
 }
 
 impl Default for SharedState {
@@ -557,8 +866,33 @@ impl Default for SharedState {
             plugin_api: PluginApi::Clap,
             current_tab: Tab::Live,
             program: StagedProgram::default(),
-            active_notes: ActiveNotes::default(),
-            last_program_beat: -1.0,
+            launch_quantization: LaunchQuantization::default(),
+            euclid_voices: [
+                // Kick: four-on-the-floor-ish
+                euclid::EuclidVoice { pulses: 4, steps: 16, rotation: 0, pitch: 36, velocity: 0.95, step_length_beats: 0.25 },
+                // Snare: backbeat-ish, rotated off the downbeat
+                euclid::EuclidVoice { pulses: 2, steps: 16, rotation: 4, pitch: 38, velocity: 0.85, step_length_beats: 0.25 },
+                // Hat: dense, near-constant pulse
+                euclid::EuclidVoice { pulses: 11, steps: 16, rotation: 0, pitch: 42, velocity: 0.6, step_length_beats: 0.25 },
+            ],
+            echo_repeats: 3,
+            echo_offset_beats: 0.25,
+            echo_decay: 0.7,
+            scale_root: 60,
+            scale_is_minor: false,
+            humanize: humanize::HumanizeParams::default(),
+            recording: false,
+            record_quantize: capture::QuantizeGrid::Sixteenth,
+            record: capture::RecordState::default(),
+            piano_roll: piano_roll::PianoRollState::default(),
+            control_surface: control_surface::ControlSurfaceState::default(),
+            metronome: metronome::MetronomeConfig::default(),
+            click: metronome::ClickState::default(),
+            pitch_track: pitch_track::PitchTrackConfig::default(),
+            wasm_dsp: wasm_dsp::WasmDspConfig::default(),
+            load_meter: load_meter::LoadMeter::default(),
+            sync_status: sync::ConnectionStatus::default(),
+            sync_last_update: None,
         }
     }
 }
@@ -568,12 +902,74 @@ pub struct Skipper {
     params: Arc<SkipperParams>,
     state: Arc<AtomicRefCell<SharedState>>,
     instance_id: u32,
+    // Real-time-safe mirror of `SharedState::program`, so the audio thread's
+    // note emission never has to borrow GUI-owned state (see `triple_buffer`).
+    program_buf: Arc<triple_buffer::TripleBuffer<StagedProgram>>,
+    program_reader_idx: usize,
+    // Audio-thread-to-GUI transport mirror (see `transport_ring`); the audio
+    // thread only ever pushes and never borrows `state` to do it.
+    transport_ring: Arc<transport_ring::TransportRing>,
+    // Audio-thread-only playback bookkeeping, previously in `SharedState`
+    // behind a borrow the emission loop could lose to GUI contention.
+    active_notes: ActiveNotes,
+    last_program_beat: f64, // Last beat position we processed (for note triggers)
+    // Absolute (un-looped) beat position expected at the start of the next
+    // block; a mismatch means the host looped or seeked since last process(),
+    // so outstanding note-offs need flushing instead of being scheduled normally.
+    expected_next_beat: f64,
+    // Opportunistically refreshed copy of `SharedState::humanize`, so the
+    // unconditional emission path never needs to borrow `state` for it.
+    humanize_cache: humanize::HumanizeParams,
+    // Quantized program launch: a program armed by `stage_program_launch`
+    // awaiting its bar/beat boundary, plus the boundary itself once computed.
+    pending_launch_buf: Arc<triple_buffer::TripleBuffer<PendingLaunch>>,
+    pending_launch_reader_idx: usize,
+    pending_armed: Arc<AtomicBool>,
+    // Absolute beat position (same clock as `pos_beats()`) the armed launch
+    // is waiting to cross; computed once on arrival, cleared once it fires.
+    pending_boundary_beats: Option<f64>,
+    // Audio-to-MIDI pitch tracking: ring buffer, analysis scratch, and held
+    // note, all audio-thread-only - see `ActiveNotes` for the same pattern.
+    pitch_track_state: pitch_track::PitchTrackState,
+    // Opportunistically refreshed copy of `SharedState::pitch_track`, same
+    // reasoning as `humanize_cache`.
+    pitch_track_cache: pitch_track::PitchTrackConfig,
+    // Lock-free mirror of the host's track metadata - see `TrackInfoSnapshot`.
+    track_info_buf: Arc<triple_buffer::TripleBuffer<TrackInfoSnapshot>>,
+    track_info_reader_idx: usize,
+    // User-supplied WASM DSP chain: the wasmtime engine and control-message
+    // queue the GUI pushes into, plus the currently active chain, handed to
+    // the audio thread through the same triple-buffer swap as `program_buf`
+    // - see `wasm_dsp` module docs.
+    wasm_host: wasm_dsp::WasmDspHost,
+    wasm_chain_buf: Arc<triple_buffer::TripleBuffer<Arc<Vec<wasm_dsp::ModuleInstance>>>>,
+    wasm_chain_reader_idx: usize,
+    wasm_reload_requested: Arc<AtomicBool>,
+    // Opportunistically refreshed copy of `SharedState::wasm_dsp.enabled` -
+    // just the one bool, since the config's `module_dir` would require an
+    // allocating clone every block (same reasoning as `humanize_cache`).
+    wasm_dsp_enabled: bool,
+    // Reused interleaved scratch buffer for handing blocks to WASM modules
+    // - sized once in `initialize()`, never reallocated in `process()`.
+    wasm_scratch: Vec<f32>,
+    // Background threads spawned from `initialize_impl` - `None` until then
+    // (and after `deactivate`/`Drop` has torn them down). Holding these is
+    // what stops every plugin instantiate/destroy cycle from leaking an OS
+    // thread plus the state/buffers it captured; see `sync::SyncHandle` and
+    // `wasm_dsp::ReloadWatcherHandle`.
+    sync_handle: Option<sync::SyncHandle>,
+    wasm_reload_handle: Option<wasm_dsp::ReloadWatcherHandle>,
 }
 
 #[derive(Params)]
 struct SkipperParams {
     #[persist = "editor-state"]
     editor_state: Arc<EguiState>,
+    // The settings `persistence` round-trips across host sessions, as a TOML
+    // document - see that module's docs for why this `#[persist]` field is
+    // the reachable seam rather than a hand-rolled `clap_plugin_state`.
+    #[persist = "skipper-settings"]
+    persisted_settings: Arc<RwLock<String>>,
 }
 
 impl Default for Skipper {
@@ -584,55 +980,29 @@ impl Default for Skipper {
             params: Arc::new(SkipperParams::default()),
             state: Arc::new(AtomicRefCell::new(SharedState::default())),
             instance_id,
-        }
-    }
-}
-
-/// Gilligan REST API URL
-const GILLIGAN_URL: &str = "http://localhost:61170/api";
-
-/// Register with Gilligan and get any staged program
-fn register_with_gilligan(uuid: &str, track_name: &str) -> Option<serde_json::Value> {
-    nih_log!("Registering with Gilligan: uuid={}, track={}", uuid, track_name);
-
-    let url = format!("{}/register", GILLIGAN_URL);
-    let body = serde_json::json!({
-        "uuid": uuid,
-        "track": track_name
-    });
-
-    match ureq::post(&url)
-        .set("Content-Type", "application/json")
-        .send_string(&body.to_string())
-    {
-        Ok(response) => {
-            match response.into_string() {
-                Ok(text) => {
-                    nih_log!("Gilligan response: {}", text);
-                    match serde_json::from_str::<serde_json::Value>(&text) {
-                        Ok(json) => {
-                            if let Some(program) = json.get("program") {
-                                if !program.is_null() {
-                                    return Some(program.clone());
-                                }
-                            }
-                            None
-                        }
-                        Err(e) => {
-                            nih_log!("Failed to parse Gilligan response: {}", e);
-                            None
-                        }
-                    }
-                }
-                Err(e) => {
-                    nih_log!("Failed to read Gilligan response: {}", e);
-                    None
-                }
-            }
-        }
-        Err(e) => {
-            nih_log!("Failed to register with Gilligan: {}", e);
-            None
+            program_buf: Arc::new(triple_buffer::TripleBuffer::new(StagedProgram::default())),
+            program_reader_idx: triple_buffer::TripleBuffer::<StagedProgram>::INITIAL_READER_INDEX,
+            transport_ring: Arc::new(transport_ring::TransportRing::new()),
+            active_notes: ActiveNotes::default(),
+            last_program_beat: -1.0,
+            expected_next_beat: -1.0,
+            humanize_cache: humanize::HumanizeParams::default(),
+            pending_launch_buf: Arc::new(triple_buffer::TripleBuffer::new(PendingLaunch::default())),
+            pending_launch_reader_idx: triple_buffer::TripleBuffer::<PendingLaunch>::INITIAL_READER_INDEX,
+            pending_armed: Arc::new(AtomicBool::new(false)),
+            pending_boundary_beats: None,
+            pitch_track_state: pitch_track::PitchTrackState::new(),
+            pitch_track_cache: pitch_track::PitchTrackConfig::default(),
+            track_info_buf: Arc::new(triple_buffer::TripleBuffer::new(TrackInfoSnapshot::default())),
+            track_info_reader_idx: triple_buffer::TripleBuffer::<TrackInfoSnapshot>::INITIAL_READER_INDEX,
+            wasm_host: wasm_dsp::WasmDspHost::new(),
+            wasm_chain_buf: Arc::new(triple_buffer::TripleBuffer::new(Arc::new(Vec::new()))),
+            wasm_chain_reader_idx: triple_buffer::TripleBuffer::<Arc<Vec<wasm_dsp::ModuleInstance>>>::INITIAL_READER_INDEX,
+            wasm_reload_requested: Arc::new(AtomicBool::new(false)),
+            wasm_dsp_enabled: false,
+            wasm_scratch: Vec::new(),
+            sync_handle: None,
+            wasm_reload_handle: None,
         }
     }
 }
@@ -641,6 +1011,7 @@ impl Default for SkipperParams {
     fn default() -> Self {
         Self {
             editor_state: EguiState::from_size(520, 600),
+            persisted_settings: Arc::new(RwLock::new(String::new())),
         }
     }
 }
@@ -678,6 +1049,7 @@ fn build_info_text(shared: &SharedState, track_info: &Option<Arc<TrackInfo>>) ->
     lines.push(format!("Version:     {}", Skipper::VERSION));
     lines.push(format!("Sample Rate: {:.0} Hz", shared.sample_rate));
     lines.push(format!("Buffer Size: {} samples", shared.buffer_size));
+    lines.push(format!("DSP Load:    {:.1}% (peak {:.1}%)", shared.load_meter.smoothed_pct, shared.load_meter.peak_pct));
     lines.push(String::new());
 
     // Host info
@@ -693,6 +1065,23 @@ fn build_info_text(shared: &SharedState, track_info: &Option<Arc<TrackInfo>>) ->
     }
     lines.push(String::new());
 
+    // Gilligan sync status
+    lines.push("--------------------------------------------------".to_string());
+    lines.push("SYNC".to_string());
+    lines.push("--------------------------------------------------".to_string());
+    let sync_status_str = match shared.sync_status {
+        sync::ConnectionStatus::Offline => "OFFLINE",
+        sync::ConnectionStatus::Connected => "CONNECTED",
+        sync::ConnectionStatus::Reconnecting => "RECONNECTING",
+    };
+    lines.push(format!("Status:      {}", sync_status_str));
+    let last_update_str = match shared.sync_last_update.and_then(|t| t.elapsed().ok()) {
+        Some(elapsed) => format!("{}s ago", elapsed.as_secs()),
+        None => "(never)".to_string(),
+    };
+    lines.push(format!("Last Update: {}", last_update_str));
+    lines.push(String::new());
+
     // Track info details
     lines.push("--------------------------------------------------".to_string());
     lines.push("TRACK INFO".to_string());
@@ -756,9 +1145,12 @@ fn build_info_text(shared: &SharedState, track_info: &Option<Arc<TrackInfo>>) ->
 
     let position = match shared.transport.pos_beats {
         Some(beats) => {
-            let time_sig_num = shared.transport.time_sig_numerator.unwrap_or(4) as f64;
-            let bars = (beats / time_sig_num).floor() as i32 + 1;
-            let beat_in_bar = (beats % time_sig_num) + 1.0;
+            let beats_per_bar = schedule::beats_per_bar(
+                shared.transport.time_sig_numerator.unwrap_or(4),
+                shared.transport.time_sig_denominator.unwrap_or(4),
+            );
+            let bars = (beats / beats_per_bar).floor() as i32 + 1;
+            let beat_in_bar = (beats % beats_per_bar) + 1.0;
             format!("Bar {} | Beat {:.2}", bars, beat_in_bar)
         }
         None => "(not available)".to_string(),
@@ -880,9 +1272,12 @@ fn render_live_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Option<
 
             // Position
             if let Some(beats) = shared.transport.pos_beats {
-                let time_sig_num = shared.transport.time_sig_numerator.unwrap_or(4) as f64;
-                let bars = (beats / time_sig_num).floor() as i32 + 1;
-                let beat_in_bar = (beats % time_sig_num) + 1.0;
+                let beats_per_bar = schedule::beats_per_bar(
+                    shared.transport.time_sig_numerator.unwrap_or(4),
+                    shared.transport.time_sig_denominator.unwrap_or(4),
+                );
+                let bars = (beats / beats_per_bar).floor() as i32 + 1;
+                let beat_in_bar = (beats % beats_per_bar) + 1.0;
                 ui.label(egui::RichText::new(format!("Bar {} : Beat {:.2}", bars, beat_in_bar)).size(18.0).monospace());
             }
 
@@ -913,12 +1308,264 @@ fn render_live_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Option<
 }
 
 /// Render the Program tab showing staged/current program
-fn render_program_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Option<Arc<nih_plug::prelude::TrackInfo>>) {
+/// Render the live-capture record toggle and quantize-grid selector.
+fn render_capture_controls(ui: &mut egui::Ui, recording: &mut bool, grid: &mut capture::QuantizeGrid) {
+    egui::CollapsingHeader::new("Live Capture")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let label = if *recording { "Stop Recording" } else { "Record" };
+                let color = if *recording {
+                    egui::Color32::from_rgb(255, 50, 50)
+                } else {
+                    egui::Color32::GRAY
+                };
+                if ui.button(egui::RichText::new(label).color(color)).clicked() {
+                    *recording = !*recording;
+                }
+                ui.label(format!("(4-beat count-in, grid: {})", match grid {
+                    capture::QuantizeGrid::Off => "off",
+                    capture::QuantizeGrid::Quarter => "1/4",
+                    capture::QuantizeGrid::Eighth => "1/8",
+                    capture::QuantizeGrid::Sixteenth => "1/16",
+                }));
+            });
+            ui.horizontal(|ui| {
+                ui.selectable_value(grid, capture::QuantizeGrid::Off, "Off");
+                ui.selectable_value(grid, capture::QuantizeGrid::Quarter, "1/4");
+                ui.selectable_value(grid, capture::QuantizeGrid::Eighth, "1/8");
+                ui.selectable_value(grid, capture::QuantizeGrid::Sixteenth, "1/16");
+            });
+        });
+}
+
+/// Render swing/timing/velocity humanize controls, edited in place.
+fn render_humanize_controls(ui: &mut egui::Ui, params: &mut humanize::HumanizeParams) {
+    egui::CollapsingHeader::new("Performance (Swing & Humanize)")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.add(egui::Slider::new(&mut params.swing_amount, 0.0..=1.0).text("Swing"));
+            ui.add(egui::Slider::new(&mut params.max_swing_beats, 0.0..=0.25).text("Max swing (beats)"));
+            ui.add(egui::Slider::new(&mut params.timing_jitter_beats, 0.0..=0.1).text("Timing jitter (beats)"));
+            ui.add(egui::Slider::new(&mut params.velocity_jitter, 0.0..=1.0).text("Velocity jitter"));
+        });
+}
+
+/// Render the quantized-launch grid picker for program loads/switches.
+fn render_launch_quantization_controls(ui: &mut egui::Ui, quantization: &mut LaunchQuantization) {
+    egui::CollapsingHeader::new("Launch Quantization")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(quantization, LaunchQuantization::Immediate, "Immediate");
+                ui.selectable_value(quantization, LaunchQuantization::NextBeat, "Next Beat");
+                ui.selectable_value(quantization, LaunchQuantization::NextBar, "Next Bar");
+                ui.selectable_value(quantization, LaunchQuantization::Bars(2), "2 Bars");
+                ui.selectable_value(quantization, LaunchQuantization::Bars(4), "4 Bars");
+            });
+            ui.label(egui::RichText::new("Applies to SMF imports and Gilligan pushes; in-place edits (piano roll, Euclid, transforms, capture) still take effect immediately.")
+                .size(11.0)
+                .color(egui::Color32::GRAY));
+        });
+}
+
+/// Render the metronome enable toggle and volume slider.
+fn render_metronome_controls(ui: &mut egui::Ui, config: &mut metronome::MetronomeConfig) {
+    egui::CollapsingHeader::new("Metronome")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.checkbox(&mut config.enabled, "Enabled");
+            ui.add(egui::Slider::new(&mut config.volume, 0.0..=1.0).text("Volume"));
+        });
+}
+
+/// Render the audio-to-MIDI pitch tracker's enable toggle and gate slider.
+fn render_pitch_track_controls(ui: &mut egui::Ui, config: &mut pitch_track::PitchTrackConfig) {
+    egui::CollapsingHeader::new("Audio-to-MIDI Pitch Tracking")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.checkbox(&mut config.enabled, "Enabled");
+            ui.add(egui::Slider::new(&mut config.gate_threshold, 0.0..=0.2).text("Gate (RMS)"));
+            ui.label(egui::RichText::new("Tracks the audio input's fundamental pitch and emits it as NoteOn/NoteOff - for monophonic voice or instrument input.")
+                .size(11.0)
+                .color(egui::Color32::GRAY));
+        });
+}
+
+/// Render the WASM DSP chain's config, a manual reload button, and a
+/// read-only list of whatever modules are currently loaded.
+fn render_wasm_dsp_controls(
+    ui: &mut egui::Ui,
+    config: &mut wasm_dsp::WasmDspConfig,
+    loaded: &[wasm_dsp::ModuleDescriptor],
+    on_reload: impl FnOnce(),
+) {
+    egui::CollapsingHeader::new("WASM DSP Modules")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.checkbox(&mut config.enabled, "Enabled");
+            ui.horizontal(|ui| {
+                ui.label("Module dir:");
+                let mut dir = config.module_dir.to_string_lossy().into_owned();
+                if ui.text_edit_singleline(&mut dir).changed() {
+                    config.module_dir = PathBuf::from(dir);
+                }
+            });
+            if ui.button("Rescan Now").clicked() {
+                on_reload();
+            }
+            if loaded.is_empty() {
+                ui.label(egui::RichText::new("(no modules loaded)").size(11.0).color(egui::Color32::GRAY));
+            } else {
+                for module in loaded {
+                    ui.label(format!("{} v{} ({} params)", module.name, module.version, module.params.len()));
+                }
+            }
+            ui.label(egui::RichText::new("Sandboxed *.wasm effects dropped into the module directory, run on the incoming audio as extra DSP stages before the metronome click is mixed in.")
+                .size(11.0)
+                .color(egui::Color32::GRAY));
+        });
+}
+
+/// Render the control-surface toggle and base-note field. Row pitches keep
+/// their defaults here; editing them is a job for a future per-row editor.
+fn render_control_surface_controls(ui: &mut egui::Ui, config: &mut control_surface::ControlSurfaceConfig) {
+    egui::CollapsingHeader::new("Control Surface (8x8 Pad Grid)")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.checkbox(&mut config.enabled, "Enabled");
+            ui.horizontal(|ui| {
+                let mut base_note = config.base_note as i32;
+                ui.add(egui::DragValue::new(&mut base_note).clamp_range(0..=(127 - control_surface::TOTAL_PADS as i32)).prefix("base note="));
+                config.base_note = base_note.clamp(0, 127 - control_surface::TOTAL_PADS as i32) as u8;
+            });
+            ui.label(egui::RichText::new("Pads light by note-on velocity only (brightness follows the track color, no RGB); transport pads are LED-only (no host transport control).")
+                .size(11.0)
+                .color(egui::Color32::GRAY));
+        });
+}
+
+/// Render editable fields for each Euclidean voice plus a "Generate" button.
+/// Returns `true` if the user clicked Generate.
+fn render_euclid_generator(ui: &mut egui::Ui, voices: &mut [euclid::EuclidVoice; 3]) -> bool {
+    let mut generate = false;
+    let labels = ["Kick", "Snare", "Hat"];
+
+    egui::CollapsingHeader::new("Euclidean Generator")
+        .default_open(false)
+        .show(ui, |ui| {
+            for (voice, label) in voices.iter_mut().zip(labels.iter()) {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{:>5}", label));
+                    ui.add(egui::DragValue::new(&mut voice.pulses).clamp_range(0..=64).prefix("k="));
+                    ui.add(egui::DragValue::new(&mut voice.steps).clamp_range(1..=64).prefix("n="));
+                    ui.add(egui::DragValue::new(&mut voice.rotation).clamp_range(0..=64).prefix("rot="));
+                    let mut pitch = voice.pitch as i32;
+                    ui.add(egui::DragValue::new(&mut pitch).clamp_range(0..=127).prefix("pitch="));
+                    voice.pitch = pitch.clamp(0, 127) as u8;
+                    ui.add(egui::DragValue::new(&mut voice.velocity).clamp_range(0.0..=1.0).speed(0.01).prefix("vel="));
+                    ui.add(egui::DragValue::new(&mut voice.step_length_beats).clamp_range(0.0625..=4.0).speed(0.01).prefix("len="));
+                });
+            }
+            if ui.button("Generate").clicked() {
+                generate = true;
+            }
+        });
+
+    generate
+}
+
+/// Which transform, if any, the user requested this frame from the
+/// transform toolbar in the Program tab.
+enum TransformAction {
+    Reverse,
+    Echo,
+    ScaleQuantize,
+}
+
+/// Render the pattern-transform toolbar (reverse / echo / scale-quantize),
+/// editing the echo/scale parameters in place. Returns the requested
+/// transform, if the user clicked one of the buttons this frame.
+fn render_transform_toolbar(
+    ui: &mut egui::Ui,
+    echo_repeats: &mut u32,
+    echo_offset_beats: &mut f64,
+    echo_decay: &mut f32,
+    scale_root: &mut u8,
+    scale_is_minor: &mut bool,
+) -> Option<TransformAction> {
+    let mut action = None;
+
+    egui::CollapsingHeader::new("Transforms")
+        .default_open(false)
+        .show(ui, |ui| {
+            if ui.button("Reverse").clicked() {
+                action = Some(TransformAction::Reverse);
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Echo");
+                ui.add(egui::DragValue::new(echo_repeats).clamp_range(1..=16).prefix("n="));
+                ui.add(egui::DragValue::new(echo_offset_beats).clamp_range(0.0..=4.0).speed(0.01).prefix("offset="));
+                ui.add(egui::DragValue::new(echo_decay).clamp_range(0.0..=1.0).speed(0.01).prefix("decay="));
+                if ui.button("Apply").clicked() {
+                    action = Some(TransformAction::Echo);
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Scale Quantize");
+                let mut root = *scale_root as i32;
+                ui.add(egui::DragValue::new(&mut root).clamp_range(0..=127).prefix("root="));
+                *scale_root = root.clamp(0, 127) as u8;
+                ui.selectable_value(scale_is_minor, false, "Major");
+                ui.selectable_value(scale_is_minor, true, "Minor");
+                if ui.button("Apply").clicked() {
+                    action = Some(TransformAction::ScaleQuantize);
+                }
+            });
+        });
+
+    action
+}
+
+/// Requested from `render_program_tab` when the user clicks an SMF
+/// import/export button; applied by the caller, which holds the mutable state.
+enum ProgramTabAction {
+    ImportSmf(PathBuf),
+    ExportSmf,
+}
+
+/// Render the Program tab showing staged/current program
+fn render_program_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Option<Arc<nih_plug::prelude::TrackInfo>>) -> Option<ProgramTabAction> {
+    let mut action = None;
+
     egui::ScrollArea::vertical()
         .auto_shrink([false, false])
         .show(ui, |ui| {
             let program = &shared.program;
 
+            // SMF import/export controls
+            ui.horizontal(|ui| {
+                if ui.button("Export .mid").clicked() {
+                    action = Some(ProgramTabAction::ExportSmf);
+                }
+                ui.label(format!("(staged to {})", STAGING_DIR));
+            });
+            let mid_files = smf::find_mid_files(std::path::Path::new(STAGING_DIR));
+            if !mid_files.is_empty() {
+                ui.label("Import from staging dir:");
+                for path in &mid_files {
+                    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+                    if ui.button(format!("Import {}", file_name)).clicked() {
+                        action = Some(ProgramTabAction::ImportSmf(path.clone()));
+                    }
+                }
+            }
+            ui.add_space(8.0);
+
             // Program header
             ui.heading("Staged Program");
             ui.add_space(8.0);
@@ -942,6 +1589,8 @@ fn render_program_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Opti
                     ui.label("•");
                     ui.label(format!("{} beats", program.length_beats));
                     ui.label("•");
+                    ui.label(format!("{}/{}", program.time_sig_numerator, program.time_sig_denominator));
+                    ui.label("•");
                     ui.label(format!("{} notes", program.note_count));
                 });
 
@@ -973,8 +1622,9 @@ fn render_program_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Opti
                         continue;
                     }
 
-                    let bar = (note.start_beat / 4.0).floor() as i32 + 1;
-                    let beat = (note.start_beat % 4.0) + 1.0;
+                    let beats_per_bar = program.beats_per_bar();
+                    let bar = (note.start_beat / beats_per_bar).floor() as i32 + 1;
+                    let beat = (note.start_beat % beats_per_bar) + 1.0;
                     let note_name = StagedProgram::pitch_to_name(note.pitch);
 
                     // Highlight current beat position
@@ -1019,9 +1669,10 @@ fn render_program_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Opti
                 // Current position in program
                 ui.heading("Playback");
                 if let Some(pos_beats) = shared.transport.pos_beats {
+                    let beats_per_bar = program.beats_per_bar();
                     let program_beat = pos_beats % program.length_beats;
-                    let program_bar = (program_beat / 4.0).floor() as i32 + 1;
-                    let beat_in_bar = (program_beat % 4.0) + 1.0;
+                    let program_bar = (program_beat / beats_per_bar).floor() as i32 + 1;
+                    let beat_in_bar = (program_beat % beats_per_bar) + 1.0;
 
                     ui.label(egui::RichText::new(
                         format!("Program position: Bar {} Beat {:.2}", program_bar, beat_in_bar)
@@ -1048,6 +1699,374 @@ fn render_program_tab(ui: &mut egui::Ui, shared: &SharedState, track_info: &Opti
                     .size(12.0));
             }
         });
+
+    action
+}
+
+/// Send a NoteOff for every pitch currently believed to be sounding, at
+/// `timing` (a sample offset into the current block). Used when the
+/// transport stops, when a loop/seek discontinuity invalidates our
+/// per-pitch end-beat tracking (both block-granularity triggers with no
+/// meaningful sub-block sample to time to, so `timing: 0` is as accurate as
+/// it gets), and when a quantized program swap lands mid-block (where the
+/// caller passes the boundary's actual sample offset).
+fn flush_all_notes_off(active_notes: &mut ActiveNotes, context: &mut impl ProcessContext<Skipper>, timing: u32) {
+    for channel in 0u8..MIDI_CHANNELS as u8 {
+        for pitch in 0u8..128 {
+            if active_notes.is_playing(channel, pitch) {
+                context.send_event(NoteEvent::NoteOff {
+                    timing,
+                    voice_id: None,
+                    channel,
+                    note: pitch,
+                    velocity: 0.0,
+                });
+                active_notes.clear_playing(channel, pitch);
+            }
+        }
+    }
+}
+
+/// Smallest multiple of `grid_beats` that is at or after `pos_beats` - the
+/// next bar/beat boundary a quantized launch waits for. Guards against
+/// floating-point rounding putting the result a hair before `pos_beats`.
+fn next_grid_boundary(pos_beats: f64, grid_beats: f64) -> f64 {
+    if grid_beats <= 0.0 {
+        return pos_beats;
+    }
+    let boundary = (pos_beats / grid_beats).ceil() * grid_beats;
+    if boundary < pos_beats {
+        boundary + grid_beats
+    } else {
+        boundary
+    }
+}
+
+/// Stage `program` to become the active program. `Immediate` swaps it into
+/// the audio thread's playback mirror right away; any other quantization
+/// arms it instead, for `process()` to swap in once the transport crosses
+/// the matching bar/beat boundary.
+fn stage_program_launch(
+    program_buf: &triple_buffer::TripleBuffer<StagedProgram>,
+    pending_launch_buf: &triple_buffer::TripleBuffer<PendingLaunch>,
+    pending_armed: &AtomicBool,
+    quantization: LaunchQuantization,
+    program: StagedProgram,
+) {
+    if quantization == LaunchQuantization::Immediate {
+        program_buf.write(|p| *p = program);
+    } else {
+        pending_launch_buf.write(|p| *p = PendingLaunch { program, quantization });
+        pending_armed.store(true, Ordering::Release);
+    }
+}
+
+/// One event [`schedule_program_block`] decided to fire for a sub-block,
+/// kept as plain data instead of calling `context.send_event` directly so
+/// that decision logic - the part with the actual scheduling/wrap math - can
+/// be unit tested without a live `ProcessContext`. [`emit_program_block`] is
+/// the thin wrapper that turns these into real `NoteEvent`s.
+#[derive(Debug, Clone, PartialEq)]
+enum ScheduledEvent {
+    NoteOn { timing: u32, channel: u8, note: u8, velocity: f32 },
+    NoteOff { timing: u32, channel: u8, note: u8 },
+    PitchBend { timing: u32, channel: u8, value: f32 },
+    PolyPressure { timing: u32, channel: u8, note: u8, value: f32 },
+    MidiCc { timing: u32, channel: u8, cc: u8, value: f32 },
+}
+
+/// Decide note-on/off and expression events for one sub-block of playback:
+/// `program_beat_start` is this sub-block's position within `program`'s
+/// loop, `block_beats` its length in beats, and `sample_bias` the sample
+/// offset (into the full audio buffer) this sub-block actually starts at -
+/// nonzero only when a quantized program swap lands mid-block and a new
+/// program takes over partway through.
+#[allow(clippy::too_many_arguments)]
+fn schedule_program_block(
+    program: &StagedProgram,
+    program_beat_start: f64,
+    block_beats: f64,
+    sample_bias: i32,
+    beats_per_sample: f64,
+    buffer_len: usize,
+    humanize_params: &humanize::HumanizeParams,
+    active_notes: &mut ActiveNotes,
+) -> Vec<ScheduledEvent> {
+    let mut events = Vec::new();
+    let program_length = program.length_beats;
+    let (seg1, seg2) = schedule::loop_segments(program_beat_start, block_beats, program_length);
+
+    for i in 0..program.note_count {
+        let note = &program.notes[i];
+        if !note.active {
+            continue;
+        }
+
+        // Apply the humanized performance layer at trigger time - the
+        // stored note is never mutated.
+        let (effective_start, effective_velocity) = humanize::apply(note, i, humanize_params);
+        let note_end = effective_start + note.length_beats;
+        let pitch = note.pitch;
+        let channel = note.channel;
+
+        // Note-on: does this note's (humanized) start land in either
+        // segment of this sub-block?
+        let note_on_offset = schedule::offset_in_segment(effective_start, &seg1)
+            .or_else(|| seg2.as_ref().and_then(|seg2| schedule::offset_in_segment(effective_start, seg2)));
+
+        if let Some(beats_from_start) = note_on_offset {
+            if !active_notes.is_playing(channel, pitch) {
+                let offset = sample_bias + schedule::sample_offset(beats_from_start, beats_per_sample, buffer_len);
+                events.push(ScheduledEvent::NoteOn {
+                    timing: offset.clamp(0, buffer_len as i32 - 1) as u32,
+                    channel,
+                    note: pitch,
+                    velocity: effective_velocity,
+                });
+                active_notes.set_playing(channel, pitch, note_end);
+            }
+        }
+
+        // Note-off: does the currently-playing end beat for this pitch land
+        // in either segment (normalized for wrap)?
+        if active_notes.is_playing(channel, pitch) {
+            let note_end_beat = active_notes.end_beats[ActiveNotes::index(channel, pitch)];
+            let note_off_offset = schedule::offset_for_note_off(note_end_beat, program_length, &seg1, seg2.as_ref());
+
+            if let Some(beats_from_start) = note_off_offset {
+                let offset = sample_bias + schedule::sample_offset(beats_from_start, beats_per_sample, buffer_len);
+                events.push(ScheduledEvent::NoteOff {
+                    timing: offset.clamp(0, buffer_len as i32 - 1) as u32,
+                    channel,
+                    note: pitch,
+                });
+                active_notes.clear_playing(channel, pitch);
+            }
+        }
+
+        // Dynamic expression lane: while this note is sounding, sample its
+        // curve at this sub-block's start beat and push the interpolated
+        // value out as the matching continuous MIDI message - the same
+        // per-sample-offset timing used for note-on/off above, just without
+        // a note-off-style end condition of its own.
+        if let Some(curve) = note.expression {
+            if active_notes.is_playing(channel, pitch) {
+                // `program_beat_start` is loop-local (reset to near zero
+                // every time the loop wraps) while `effective_start` is the
+                // note's own loop-local onset beat, so a plain subtraction
+                // goes negative for the rest of a note's life once the loop
+                // has wrapped since it started - `rem_euclid` is the
+                // wrap-aware version of the same subtraction: it's a no-op
+                // when nothing has wrapped, and folds back into
+                // `[0, program_length)` the same way the loop position
+                // itself does when it has.
+                let elapsed = if program_length > 0.0 {
+                    (program_beat_start - effective_start).rem_euclid(program_length)
+                } else {
+                    (program_beat_start - effective_start).max(0.0)
+                };
+                let t = if note.length_beats > 0.0 {
+                    (elapsed / note.length_beats).clamp(0.0, 1.0) as f32
+                } else {
+                    1.0
+                };
+                let value = match curve.interpolation {
+                    Interpolation::Linear => curve.start + (curve.end - curve.start) * t,
+                    Interpolation::Step => if t >= 1.0 { curve.end } else { curve.start },
+                };
+                let timing = sample_bias.clamp(0, buffer_len as i32 - 1) as u32;
+                events.push(match curve.target {
+                    ExpressionTarget::PitchBend => ScheduledEvent::PitchBend { timing, channel, value },
+                    ExpressionTarget::Pressure => ScheduledEvent::PolyPressure { timing, channel, note: pitch, value },
+                    ExpressionTarget::Volume => ScheduledEvent::MidiCc { timing, channel, cc: 7, value },
+                    ExpressionTarget::Pan => ScheduledEvent::MidiCc { timing, channel, cc: 10, value },
+                });
+            }
+        }
+    }
+
+    events
+}
+
+/// Emit note-on/off/expression events for one sub-block of playback by
+/// deciding them with [`schedule_program_block`] and sending each one
+/// through `context` - see that function's doc comment for the parameters.
+#[allow(clippy::too_many_arguments)]
+fn emit_program_block(
+    program: &StagedProgram,
+    program_beat_start: f64,
+    block_beats: f64,
+    sample_bias: i32,
+    beats_per_sample: f64,
+    buffer_len: usize,
+    humanize_params: &humanize::HumanizeParams,
+    active_notes: &mut ActiveNotes,
+    context: &mut impl ProcessContext<Skipper>,
+) {
+    let events = schedule_program_block(
+        program,
+        program_beat_start,
+        block_beats,
+        sample_bias,
+        beats_per_sample,
+        buffer_len,
+        humanize_params,
+        active_notes,
+    );
+
+    for event in events {
+        match event {
+            ScheduledEvent::NoteOn { timing, channel, note, velocity } => {
+                context.send_event(NoteEvent::NoteOn { timing, voice_id: None, channel, note, velocity });
+            }
+            ScheduledEvent::NoteOff { timing, channel, note } => {
+                context.send_event(NoteEvent::NoteOff { timing, voice_id: None, channel, note, velocity: 0.0 });
+            }
+            ScheduledEvent::PitchBend { timing, channel, value } => {
+                context.send_event(NoteEvent::MidiPitchBend { timing, channel, value });
+            }
+            ScheduledEvent::PolyPressure { timing, channel, note, value } => {
+                context.send_event(NoteEvent::PolyPressure { timing, voice_id: None, channel, note, pressure: value });
+            }
+            ScheduledEvent::MidiCc { timing, channel, cc, value } => {
+                context.send_event(NoteEvent::MidiCC { timing, channel, cc, value });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod emit_program_block_tests {
+    use super::*;
+
+    fn note(start_beat: f64, length_beats: f64) -> ProgramNote {
+        ProgramNote {
+            pitch: 60,
+            velocity: 1.0,
+            start_beat,
+            length_beats,
+            active: true,
+            channel: 0,
+            expression: None,
+        }
+    }
+
+    fn program_with(notes: &[ProgramNote], length_beats: f64) -> StagedProgram {
+        let mut program = StagedProgram { length_beats, loaded: true, ..StagedProgram::default() };
+        for (i, n) in notes.iter().enumerate() {
+            program.notes[i] = *n;
+        }
+        program.note_count = notes.len();
+        program
+    }
+
+    fn schedule(
+        program: &StagedProgram,
+        program_beat_start: f64,
+        block_beats: f64,
+        active_notes: &mut ActiveNotes,
+    ) -> Vec<ScheduledEvent> {
+        schedule_program_block(
+            program,
+            program_beat_start,
+            block_beats,
+            0,
+            // 1 beat/sample keeps sample offsets numerically equal to beat
+            // offsets, so assertions below can compare against beat math
+            // directly instead of re-deriving a sample rate.
+            1.0,
+            1_000_000,
+            &humanize::HumanizeParams::default(),
+            active_notes,
+        )
+    }
+
+    #[test]
+    fn ordinary_note_on_and_off_within_one_block() {
+        let program = program_with(&[note(1.0, 2.0)], 16.0);
+        let mut active_notes = ActiveNotes::default();
+
+        let events = schedule(&program, 0.0, 4.0, &mut active_notes);
+        assert_eq!(
+            events,
+            vec![
+                ScheduledEvent::NoteOn { timing: 1, channel: 0, note: 60, velocity: 1.0 },
+                ScheduledEvent::NoteOff { timing: 3, channel: 0, note: 60 },
+            ]
+        );
+        assert!(!active_notes.is_playing(0, 60));
+    }
+
+    #[test]
+    fn loop_wrapping_note_gets_continuing_expression_values_after_the_wrap() {
+        // A 4-beat note starting 2 beats before the end of an 8-beat loop,
+        // so it sustains across the loop point, with a linear pitch-bend
+        // curve from 0.0 to 1.0 over its span.
+        let mut n = note(6.0, 4.0);
+        n.expression = Some(ExpressionCurve { target: ExpressionTarget::PitchBend, start: 0.0, end: 1.0, interpolation: Interpolation::Linear });
+        let program = program_with(&[n], 8.0);
+        let mut active_notes = ActiveNotes::default();
+
+        // First block: note-on at beat 6, loop-local, no wrap yet.
+        let events = schedule(&program, 6.0, 1.0, &mut active_notes);
+        assert!(matches!(events[0], ScheduledEvent::NoteOn { .. }));
+        assert!(active_notes.is_playing(0, 60));
+
+        // Second block starts at loop-local beat 7 - still before the wrap,
+        // one beat into the note (elapsed = 1, t = 0.25).
+        let events = schedule(&program, 7.0, 1.0, &mut active_notes);
+        let value = events.iter().find_map(|e| match e {
+            ScheduledEvent::PitchBend { value, .. } => Some(*value),
+            _ => None,
+        });
+        assert_eq!(value, Some(0.25));
+
+        // Third block: the loop has wrapped (program_beat_start resets to
+        // 1.0), but the note is still sounding - 3 beats elapsed since its
+        // beat-6 onset (2 beats to the wrap, then 1 more beat), so t should keep
+        // climbing toward 1.0 instead of resetting toward 0.0.
+        let events = schedule(&program, 1.0, 1.0, &mut active_notes);
+        let value = events.iter().find_map(|e| match e {
+            ScheduledEvent::PitchBend { value, .. } => Some(*value),
+            _ => None,
+        });
+        assert_eq!(value, Some(0.75));
+    }
+
+    #[test]
+    fn quantized_launch_boundary_crossing_offsets_the_new_programs_events() {
+        // Mirrors `process_impl`'s two-sub-block split at a quantized launch
+        // boundary: the old program plays out up to the boundary, then the
+        // new program starts fresh at beat 0, sample-biased by however many
+        // samples the first sub-block consumed.
+        let old_program = program_with(&[note(0.0, 8.0)], 16.0);
+        let new_program = program_with(&[note(0.0, 1.0)], 16.0);
+        let mut active_notes = ActiveNotes::default();
+
+        // Sub-block A: old program plays from beat 0 for 2 beats (boundary
+        // at beat 2), triggering its note-on.
+        let sub_a = schedule_program_block(
+            &old_program, 0.0, 2.0, 0, 1.0, 1_000_000, &humanize::HumanizeParams::default(), &mut active_notes,
+        );
+        assert_eq!(sub_a, vec![ScheduledEvent::NoteOn { timing: 0, channel: 0, note: 60, velocity: 1.0 }]);
+
+        // The boundary forces everything off (mirrors `flush_all_notes_off`
+        // in `process_impl`) before the new program takes over.
+        active_notes.clear_playing(0, 60);
+
+        // Sub-block B: new program starts at its own beat 0, but this
+        // sub-block's samples begin 2 beats into the buffer (sample_bias).
+        let sub_b = schedule_program_block(
+            &new_program, 0.0, 2.0, 2, 1.0, 1_000_000, &humanize::HumanizeParams::default(), &mut active_notes,
+        );
+        assert_eq!(
+            sub_b,
+            vec![
+                ScheduledEvent::NoteOn { timing: 2, channel: 0, note: 60, velocity: 1.0 },
+                ScheduledEvent::NoteOff { timing: 3, channel: 0, note: 60 },
+            ]
+        );
+    }
 }
 
 impl Plugin for Skipper {
@@ -1063,7 +2082,7 @@ impl Plugin for Skipper {
             ..AudioIOLayout::const_default()
         },
     ];
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic; // Enable MIDI input for live capture
     const MIDI_OUTPUT: MidiConfig = MidiConfig::Basic;  // Enable MIDI output for note playback
     const SAMPLE_ACCURATE_AUTOMATION: bool = false;
 
@@ -1077,7 +2096,14 @@ impl Plugin for Skipper {
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         nih_log!("Skipper editor() called (id={})", self.instance_id);
         let state = self.state.clone();
-        let instance_id = self.instance_id;
+        let program_buf = self.program_buf.clone();
+        let transport_ring = self.transport_ring.clone();
+        let pending_launch_buf = self.pending_launch_buf.clone();
+        let pending_armed = self.pending_armed.clone();
+        let persisted_settings = self.params.persisted_settings.clone();
+        let wasm_chain_buf = self.wasm_chain_buf.clone();
+        let wasm_control = self.wasm_host.control.clone();
+        let track_info_buf = self.track_info_buf.clone();
 
         create_egui_editor(
             self.params.editor_state.clone(),
@@ -1087,30 +2113,26 @@ impl Plugin for Skipper {
                 egui::CentralPanel::default().show(egui_ctx, |ui| {
                     egui_ctx.request_repaint();
 
-                    // Get latest track info from context (updated by CLAP changed callback)
+                    // Get latest track info from context (updated by CLAP changed callback).
+                    // This is re-queried fresh every frame, so a host rename/recolor that
+                    // lands after initialize() shows up here immediately; mirror it into
+                    // `track_info_buf` too so the audio thread's LED feedback (which can't
+                    // call back into the host itself - see `TrackInfoSnapshot`) stops being
+                    // stuck on the one-time query `initialize()` took.
                     let track_info = setter.raw_context.track_info();
-
-                    // Register with Gilligan when track info available and no program loaded
-                    // Keep trying until we get a program (allows staging after plugin load)
-                    let has_program = if let Ok(s) = state.try_borrow() {
-                        s.program.note_count > 0
-                    } else {
-                        true // Assume loaded if can't check
-                    };
-
-                    if !has_program {
-                        if let Some(ref info) = track_info {
-                            if let Some(ref track_name) = info.name {
-                                if !track_name.is_empty() {
-                                    let uuid = format!("skipper-{}", instance_id);
-                                    if let Some(program_json) = register_with_gilligan(&uuid, track_name) {
-                                        // Load the program from Gilligan
-                                        if let Ok(mut s) = state.try_borrow_mut() {
-                                            s.program.load_from_json(&program_json);
-                                        }
-                                    }
-                                }
-                            }
+                    track_info_buf.write(|t| *t = TrackInfoSnapshot::from_track_info(&track_info));
+
+                    // Program sync with Gilligan happens on the persistent WebSocket thread
+                    // spawned from initialize() (see sync::spawn) - nothing to poll here.
+
+                    // Pick up the audio thread's latest transport snapshot. The audio
+                    // thread only ever pushes to this ring - it never borrows `state` -
+                    // so this is the one place the GUI still needs a mutable borrow for
+                    // transport, and it's fine if it's occasionally skipped: the ring
+                    // always holds the latest position for next frame.
+                    if let Some(transport) = transport_ring.drain_latest() {
+                        if let Ok(mut s) = state.try_borrow_mut() {
+                            s.transport = transport;
                         }
                     }
 
@@ -1122,6 +2144,14 @@ impl Plugin for Skipper {
 
                     let current_tab = shared.current_tab;
 
+                    // Keep the persisted-settings blob current so a host
+                    // save captures whatever was last edited - cheap enough
+                    // to just redo every GUI frame rather than threading a
+                    // dirty flag through every control that touches it.
+                    if let Ok(mut saved) = persisted_settings.try_write() {
+                        *saved = persistence::serialize_settings(&shared);
+                    }
+
                     // Release borrow before tab clicks can mutate
                     drop(shared);
 
@@ -1153,9 +2183,132 @@ impl Plugin for Skipper {
                     match current_tab {
                         Tab::Live => {
                             render_live_tab(ui, &shared, &track_info);
+                            drop(shared);
+                            if let Ok(mut s) = state.try_borrow_mut() {
+                                render_capture_controls(ui, &mut s.recording, &mut s.record_quantize);
+                                render_humanize_controls(ui, &mut s.humanize);
+                                render_control_surface_controls(ui, &mut s.control_surface.config);
+                                render_metronome_controls(ui, &mut s.metronome);
+                                render_pitch_track_controls(ui, &mut s.pitch_track);
+                                let loaded = wasm_chain_buf.snapshot();
+                                render_wasm_dsp_controls(ui, &mut s.wasm_dsp, &loaded.iter().map(|m| m.descriptor.clone()).collect::<Vec<_>>(), || {
+                                    wasm_control.push(wasm_dsp::ControlMessage::Reload);
+                                });
+                            }
                         }
                         Tab::Program => {
-                            render_program_tab(ui, &shared, &track_info);
+                            drop(shared);
+
+                            // Euclidean generator controls need mutable access;
+                            // borrow just for this widget, then re-borrow immutably below.
+                            if let Ok(mut s) = state.try_borrow_mut() {
+                                ui.heading("Piano Roll");
+                                ui.label(egui::RichText::new("Drag to create - drag body to move - drag right edge to resize - right-click to delete - shift-click/shift-drag to select")
+                                    .size(11.0)
+                                    .color(egui::Color32::GRAY));
+                                let playhead = s.transport.pos_beats;
+                                piano_roll::render(ui, &mut s.program, &mut s.piano_roll, playhead);
+                                piano_roll::render_selection_toolbar(ui, &mut s.program, &mut s.piano_roll);
+                                ui.add_space(8.0);
+                                ui.separator();
+
+                                if render_euclid_generator(ui, &mut s.euclid_voices) {
+                                    let voices = s.euclid_voices;
+                                    euclid::render_voices(&mut s.program, &voices);
+                                }
+
+                                render_launch_quantization_controls(ui, &mut s.launch_quantization);
+
+                                let transform_action = render_transform_toolbar(
+                                    ui,
+                                    &mut s.echo_repeats,
+                                    &mut s.echo_offset_beats,
+                                    &mut s.echo_decay,
+                                    &mut s.scale_root,
+                                    &mut s.scale_is_minor,
+                                );
+                                match transform_action {
+                                    Some(TransformAction::Reverse) => {
+                                        s.program = transform::rev(&s.program);
+                                    }
+                                    Some(TransformAction::Echo) => {
+                                        s.program = transform::echo(
+                                            &s.program,
+                                            s.echo_repeats,
+                                            s.echo_offset_beats,
+                                            s.echo_decay,
+                                        );
+                                    }
+                                    Some(TransformAction::ScaleQuantize) => {
+                                        let scale: &[u8] = if s.scale_is_minor {
+                                            &transform::SCALE_MINOR
+                                        } else {
+                                            &transform::SCALE_MAJOR
+                                        };
+                                        s.program = transform::scale_quantize(&s.program, s.scale_root, scale);
+                                    }
+                                    None => {}
+                                }
+                            }
+
+                            let Ok(shared) = state.try_borrow() else {
+                                return;
+                            };
+                            let action = render_program_tab(ui, &shared, &track_info);
+                            drop(shared);
+                            match action {
+                                Some(ProgramTabAction::ImportSmf(path)) => {
+                                    if let Ok(bytes) = std::fs::read(&path) {
+                                        if let Ok(mut s) = state.try_borrow_mut() {
+                                            smf::load_smf_bytes(&mut s.program, &bytes);
+                                            let quantization = s.launch_quantization;
+                                            let program = s.program.clone();
+                                            drop(s);
+                                            // Launch through the same quantized-start path as a
+                                            // Gilligan push (see `sync::apply_message`) rather than
+                                            // the generic mirror below, so an `Immediate` import
+                                            // still swaps right away but anything else waits for
+                                            // its bar/beat boundary in `process()`.
+                                            stage_program_launch(
+                                                &program_buf,
+                                                &pending_launch_buf,
+                                                &pending_armed,
+                                                quantization,
+                                                program,
+                                            );
+                                        }
+                                    }
+                                }
+                                Some(ProgramTabAction::ExportSmf) => {
+                                    if let Ok(s) = state.try_borrow() {
+                                        let bytes = smf::export_smf_bytes(&s.program, s.transport.tempo);
+                                        let file_name = format!("{}.mid", s.program.get_name());
+                                        let path = PathBuf::from(STAGING_DIR).join(file_name);
+                                        drop(s);
+                                        if let Err(e) = std::fs::write(&path, &bytes) {
+                                            nih_log!("Failed to export SMF to {:?}: {}", path, e);
+                                        } else {
+                                            nih_log!("Exported program to {:?}", path);
+                                        }
+                                    }
+                                }
+                                None => {}
+                            }
+
+                            // Mirror the (possibly just-edited) program into the
+                            // audio thread's triple buffer. Unconditional (rather
+                            // than tracking "did anything change") whenever no
+                            // quantized launch is in flight - this only runs while
+                            // the Program tab is open, and a `write()` a GUI frame is
+                            // cheap next to everything else this tab already does.
+                            // While a launch is armed, `program_buf` is left alone so
+                            // it keeps playing the outgoing program until `process()`
+                            // swaps in the pending one at its boundary.
+                            if !pending_armed.load(Ordering::Relaxed) {
+                                if let Ok(s) = state.try_borrow() {
+                                    program_buf.write(|p| *p = s.program.clone());
+                                }
+                            }
                         }
                         Tab::Info => {
                             let info_text = build_info_text(&shared, &track_info);
@@ -1178,6 +2331,59 @@ impl Plugin for Skipper {
         _audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
         context: &mut impl InitContext<Self>,
+    ) -> bool {
+        handle_panic(false, move || self.initialize_impl(buffer_config, context))
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        handle_panic(ProcessStatus::Error("panic in process()"), move || {
+            self.process_impl(buffer, aux, context)
+        })
+    }
+
+    fn deactivate(&mut self) {
+        handle_panic((), move || {
+            nih_log!("Skipper deactivated (id={})", self.instance_id);
+        });
+        // Stop and join the background sync/reload-watcher threads here
+        // rather than waiting for `Drop` - a host can deactivate and later
+        // reactivate the same plugin instance, and `initialize_impl` always
+        // spawns fresh ones, so anything still running from before would
+        // otherwise leak alongside the new pair.
+        if let Some(mut handle) = self.sync_handle.take() {
+            handle.shutdown();
+        }
+        if let Some(mut handle) = self.wasm_reload_handle.take() {
+            handle.shutdown();
+        }
+    }
+}
+
+impl Drop for Skipper {
+    fn drop(&mut self) {
+        // Covers the case where a host destroys the plugin without a prior
+        // `deactivate()` call - `SyncHandle`/`ReloadWatcherHandle` already
+        // shut down and join on their own `Drop`, this just makes that
+        // explicit rather than relying on field-drop order.
+        if let Some(mut handle) = self.sync_handle.take() {
+            handle.shutdown();
+        }
+        if let Some(mut handle) = self.wasm_reload_handle.take() {
+            handle.shutdown();
+        }
+    }
+}
+
+impl Skipper {
+    fn initialize_impl(
+        &mut self,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
         let api = context.plugin_api();
         let host_info = context.host_info();
@@ -1214,6 +2420,8 @@ impl Plugin for Skipper {
             nih_log!("Track: (no track info available)");
         }
 
+        let track_info_snapshot = TrackInfoSnapshot::from_track_info(&track_info);
+
         {
             let mut state = self.state.borrow_mut();
             state.sample_rate = buffer_config.sample_rate;
@@ -1221,195 +2429,394 @@ impl Plugin for Skipper {
             state.plugin_api = api;
             state.host_info = host_info;
             state.track_info = track_info;
-        }
-
-        // Spawn background thread to register with Gilligan once track info is available
-        let state_clone = self.state.clone();
-        let instance_id = self.instance_id;
-        std::thread::spawn(move || {
-            // Wait for track info to be populated (up to 5 seconds)
-            for _ in 0..50 {
-                std::thread::sleep(std::time::Duration::from_millis(100));
-
-                let track_name = if let Ok(s) = state_clone.try_borrow() {
-                    s.track_info.as_ref()
-                        .and_then(|t| t.name.as_ref())
-                        .filter(|n| !n.is_empty())
-                        .cloned()
-                } else {
-                    None
-                };
-
-                if let Some(name) = track_name {
-                    // Check if already has program
-                    let has_program = if let Ok(s) = state_clone.try_borrow() {
-                        s.program.note_count > 0
-                    } else {
-                        false
-                    };
 
-                    if !has_program {
-                        let uuid = format!("skipper-{}", instance_id);
-                        if let Some(program_json) = register_with_gilligan(&uuid, &name) {
-                            if let Ok(mut s) = state_clone.try_borrow_mut() {
-                                s.program.load_from_json(&program_json);
-                            }
-                        }
-                    }
-                    break;
-                }
+            // Restore settings the host persisted for this instance, if any
+            // - nih_plug restores `#[persist]` param fields before calling
+            // `initialize()`, so `persisted_settings` already holds the
+            // saved TOML (if this is a fresh instance, it's still empty and
+            // `apply_settings` leaves every field at its just-set default).
+            if let Ok(saved) = self.params.persisted_settings.read() {
+                persistence::apply_settings(&mut state, &saved);
             }
-        });
+        }
+
+        // Mirror the initial track-info query for the audio thread;
+        // `process_impl` keeps this current after a later rename/recolor
+        // (see the note there), since `initialize()` only ever runs once.
+        self.track_info_buf.write(|t| *t = track_info_snapshot);
+
+        // Allocate the pitch tracker's ring buffer and analysis scratch up
+        // front - their sizes depend on the sample rate, so they can't be
+        // `const`-sized, but `process()` must never allocate.
+        self.pitch_track_state.prepare(buffer_config.sample_rate);
+
+        // Hold a persistent WebSocket open to Gilligan for the rest of this
+        // instance's life instead of polling once and giving up - program
+        // updates are pushed and applied as they arrive, and the connection
+        // is re-established automatically if it drops.
+        self.sync_handle = Some(sync::spawn(
+            self.state.clone(),
+            self.program_buf.clone(),
+            self.pending_launch_buf.clone(),
+            self.pending_armed.clone(),
+            self.instance_id,
+        ));
+
+        // Allocate the interleaved scratch buffer handed to WASM modules up
+        // front (stereo main I/O - see `AUDIO_IO_LAYOUTS`) - `process()`
+        // must never reallocate it.
+        self.wasm_scratch = vec![0.0; buffer_config.max_buffer_size as usize * 2];
+
+        // Initial scan + every subsequent rescan happens off the audio
+        // thread - see `wasm_dsp` module docs for the swap protocol.
+        let module_dir = self.state.borrow().wasm_dsp.module_dir.clone();
+        self.wasm_reload_handle = Some(wasm_dsp::spawn_reload_watcher(
+            self.wasm_host.engine.clone(),
+            module_dir,
+            self.wasm_reload_requested.clone(),
+            self.wasm_chain_buf.clone(),
+        ));
 
         nih_log!("Skipper initialized successfully (id={})", self.instance_id);
         nih_log!("========================================");
         true
     }
 
-    fn process(
+    fn process_impl(
         &mut self,
-        _buffer: &mut Buffer,
+        buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
         // NO LOGGING HERE - audio thread forbids allocation
-        // NOTE: Don't update track_info here - it's set in initialize() and updated
-        // via CLAP changed() callback. Updating here would deallocate on audio thread.
+        let process_start = Instant::now();
         let transport = context.transport();
 
-        // Use try_borrow_mut to avoid panic if GUI is reading state
-        // If contention, skip this update - GUI will get next one
-        if let Ok(mut state) = self.state.try_borrow_mut() {
-            // Update transport state for GUI
-            state.transport.tempo = transport.tempo;
-            state.transport.time_sig_numerator = transport.time_sig_numerator;
-            state.transport.time_sig_denominator = transport.time_sig_denominator;
-            state.transport.pos_samples = transport.pos_samples();
-            state.transport.pos_beats = transport.pos_beats();
-            state.transport.pos_seconds = transport.pos_seconds();
-            state.transport.playing = transport.playing;
-            state.transport.recording = transport.recording;
-            state.transport.loop_active = transport.loop_range_beats().is_some();
-            if let Some((start, end)) = transport.loop_range_beats() {
-                state.transport.loop_start_beats = Some(start);
-                state.transport.loop_end_beats = Some(end);
-            }
+        // Refresh `track_info_buf` every block rather than only from the
+        // editor's per-frame closure (see `Skipper::editor`) - the editor
+        // only runs while the GUI is open, so a host rename/recolor that
+        // lands with no GUI open would otherwise leave this mirror stale
+        // forever. `context.track_info()` is the same cached, `changed()`-
+        // backed read the editor already uses (see `TrackInfoSnapshot`),
+        // not a live host call, so reading it every block is as cheap as
+        // the `transport()` read just above; writing the snapshot can drop
+        // a previous `TrackInfoSnapshot`'s heap data here, same as swapping
+        // in a freshly scanned `wasm_chain_buf` chain below already does -
+        // an accepted, rare-in-practice cost, not a steady-state allocation.
+        let track_info = context.track_info();
+        self.track_info_buf.write(|t| *t = TrackInfoSnapshot::from_track_info(&track_info));
+
+        // Mirror transport for the GUI via the lock-free ring instead of a
+        // shared `RefCell` write - this always succeeds, so the GUI can no
+        // longer fall behind just because it happened to be mid-frame.
+        self.transport_ring.push(TransportState {
+            tempo: transport.tempo,
+            time_sig_numerator: transport.time_sig_numerator,
+            time_sig_denominator: transport.time_sig_denominator,
+            pos_samples: transport.pos_samples(),
+            pos_beats: transport.pos_beats(),
+            pos_seconds: transport.pos_seconds(),
+            playing: transport.playing,
+            recording: transport.recording,
+            loop_active: transport.loop_range_beats().is_some(),
+            loop_start_beats: transport.loop_range_beats().map(|(start, _)| start),
+            loop_end_beats: transport.loop_range_beats().map(|(_, end)| end),
+        });
 
-            // === MIDI Note Emission ===
-            // Only emit notes if playing and we have a loaded program
-            if transport.playing && state.program.loaded {
-                if let Some(pos_beats) = transport.pos_beats() {
-                    let program_length = state.program.length_beats;
-                    if program_length > 0.0 {
-                        // Calculate position within program (looping)
-                        let program_beat = pos_beats % program_length;
-                        let last_beat = state.last_program_beat;
-
-                        // Detect wrap: position jumped backwards significantly
-                        // Use a threshold to handle floating point precision
-                        let wrapped = last_beat >= 0.0 && program_beat < last_beat - 1.0;
-
-                        // Also detect first frame after transport start (last_beat was -1)
-                        let first_frame = last_beat < 0.0;
-
-                        // On wrap or first frame: clear all active notes
-                        if wrapped || first_frame {
-                            for pitch in 0u8..128 {
-                                if state.active_notes.is_playing(pitch) {
-                                    context.send_event(NoteEvent::NoteOff {
-                                        timing: 0,
-                                        voice_id: None,
-                                        channel: 0,
-                                        note: pitch,
-                                        velocity: 0.0,
-                                    });
-                                    state.active_notes.clear_playing(pitch);
-                                }
-                            }
+        // === Audio-to-MIDI Pitch Tracking ===
+        // Independent of the transport-driven sequencer below: tracks
+        // whatever is coming in on the audio input regardless of whether a
+        // program is playing.
+        if self.pitch_track_cache.enabled {
+            pitch_track::process_block(
+                &mut self.pitch_track_state,
+                &self.pitch_track_cache,
+                transport.sample_rate,
+                buffer,
+                context,
+            );
+        }
+
+        // === User-Supplied WASM DSP Chain ===
+        // Runs after pitch tracking so a user's effects always see the raw
+        // input, before this block's audio is touched. The active chain is
+        // the audio thread's designated triple-buffer read - the background
+        // reload-watcher thread swaps in a freshly scanned chain at the next read()
+        // once a reload is requested (see `wasm_dsp` module docs).
+        if self.wasm_dsp_enabled {
+            let chain = self.wasm_chain_buf.read(&mut self.wasm_chain_reader_idx).clone();
+            if self.wasm_host.control.drain_into(&chain) {
+                self.wasm_reload_requested.store(true, Ordering::Release);
+            }
+            if !chain.is_empty() {
+                let channel_count = buffer.channels();
+                let frame_count = buffer.samples();
+                let needed = frame_count * channel_count;
+                if needed <= self.wasm_scratch.len() {
+                    for (i, channel_samples) in buffer.iter_samples().enumerate() {
+                        for (c, sample) in channel_samples.into_iter().enumerate() {
+                            self.wasm_scratch[i * channel_count + c] = *sample;
+                        }
+                    }
+                    wasm_dsp::process_chain(&chain, &mut self.wasm_scratch[..needed], frame_count, channel_count);
+                    for (i, channel_samples) in buffer.iter_samples().enumerate() {
+                        for (c, sample) in channel_samples.into_iter().enumerate() {
+                            *sample = self.wasm_scratch[i * channel_count + c];
                         }
+                    }
+                }
+            }
+        }
 
-                        // Check each note for note-on and note-off events
-                        for i in 0..state.program.note_count {
-                            let note = &state.program.notes[i];
-                            if !note.active {
-                                continue;
-                            }
+        // === MIDI Note Emission (sample-accurate) ===
+        // Reads the program through the triple buffer and keeps its own
+        // active-notes bookkeeping, so this never depends on winning the
+        // `state` borrow below - program swaps and playback stay glitch-free
+        // even while the GUI is mid-frame. Note-on/off timing is computed to
+        // the exact sample within this block instead of landing on sample 0,
+        // so large buffer sizes no longer smear rhythm.
+        if transport.playing {
+            if let (Some(pos_beats), Some(tempo)) = (transport.pos_beats(), transport.tempo) {
+                let buffer_len = buffer.samples();
+                if buffer_len > 0 {
+                    let beats_per_sample = schedule::beats_per_sample(tempo, transport.sample_rate);
+                    let block_beats = buffer_len as f64 * beats_per_sample;
+                    let numerator = transport.time_sig_numerator.unwrap_or(4);
+                    let denominator = transport.time_sig_denominator.unwrap_or(4);
+
+                    let first_frame = self.last_program_beat < 0.0;
+
+                    // A mismatch between where we are and where the previous
+                    // block's bookkeeping expected us to be means the host
+                    // looped (region shorter than the program) or the user
+                    // seeked the playhead - our per-pitch end-beat tracking is
+                    // no longer valid, so treat it like a fresh start.
+                    let discontinuous = !first_frame && (pos_beats - self.expected_next_beat).abs() > 1e-6;
+
+                    // On first block after transport (re)starts, or after an
+                    // unexpected loop/seek, clear any still-active notes so we
+                    // never stack duplicates or leave one stuck sustaining.
+                    if first_frame || discontinuous {
+                        flush_all_notes_off(&mut self.active_notes, context, 0);
+                    }
 
-                            let note_start = note.start_beat;
-                            let note_end = note.start_beat + note.length_beats;
-                            let pitch = note.pitch;
-
-                            // Note-on: trigger if we just crossed the start beat
-                            let should_trigger = if wrapped || first_frame {
-                                // Wrap or start: trigger all notes from 0 to current position
-                                note_start <= program_beat + 0.01
-                            } else {
-                                // Normal case: did we cross the start beat?
-                                note_start > last_beat && note_start <= program_beat + 0.01
-                            };
+                    // Arm the boundary for a pending quantized launch as soon
+                    // as one shows up, so later blocks just compare against
+                    // it instead of re-deriving it every time.
+                    if self.pending_armed.load(Ordering::Acquire) && self.pending_boundary_beats.is_none() {
+                        let pending = self.pending_launch_buf.read(&mut self.pending_launch_reader_idx);
+                        let grid = pending.quantization.grid_beats(numerator, denominator).unwrap_or(0.0);
+                        self.pending_boundary_beats = Some(if grid > 0.0 {
+                            next_grid_boundary(pos_beats, grid)
+                        } else {
+                            pos_beats
+                        });
+                    }
 
-                            if should_trigger && !state.active_notes.is_playing(pitch) {
-                                // Send note-on
-                                let velocity = (note.velocity * 127.0) as u8;
-                                context.send_event(NoteEvent::NoteOn {
-                                    timing: 0,
-                                    voice_id: None,
-                                    channel: 0,
-                                    note: pitch,
-                                    velocity: note.velocity,
-                                });
-                                state.active_notes.set_playing(pitch, note_end);
+                    let crossing = self
+                        .pending_boundary_beats
+                        .filter(|&boundary| boundary >= pos_beats && boundary < pos_beats + block_beats);
+
+                    if let Some(boundary) = crossing {
+                        // Sub-block A: finish out the old program up to the boundary.
+                        let old_program = self.program_buf.read(&mut self.program_reader_idx);
+                        if old_program.loaded && old_program.length_beats > 0.0 {
+                            let program_beat_start = pos_beats % old_program.length_beats;
+                            emit_program_block(
+                                old_program,
+                                program_beat_start,
+                                boundary - pos_beats,
+                                0,
+                                beats_per_sample,
+                                buffer_len,
+                                &self.humanize_cache,
+                                &mut self.active_notes,
+                                context,
+                            );
+                        }
+
+                        // Force-stop anything still sounding right at the
+                        // boundary - the old program's per-pitch end-beat
+                        // tracking doesn't carry over to the new one.
+                        let boundary_sample = schedule::sample_offset(boundary - pos_beats, beats_per_sample, buffer_len)
+                            .clamp(0, buffer_len as i32 - 1) as u32;
+                        flush_all_notes_off(&mut self.active_notes, context, boundary_sample);
+
+                        // Swap the staged program into the realtime mirror. A
+                        // lost race just means we try again next block rather
+                        // than ever blocking the audio thread.
+                        let pending = self.pending_launch_buf.read(&mut self.pending_launch_reader_idx);
+                        if self.program_buf.try_write(|p| *p = pending.program.clone()) {
+                            self.pending_armed.store(false, Ordering::Release);
+                            self.pending_boundary_beats = None;
+
+                            // Best-effort mirror for the GUI's display copy -
+                            // see `SharedState::program`'s doc comment.
+                            if let Ok(mut state) = self.state.try_borrow_mut() {
+                                state.program = pending.program.clone();
                             }
 
-                            // Note-off: trigger if we crossed the end beat
-                            if state.active_notes.is_playing(pitch) {
-                                let note_end_beat = state.active_notes.end_beats[pitch as usize];
-                                let should_end = if wrapped {
-                                    note_end_beat > last_beat || note_end_beat <= program_beat
-                                } else {
-                                    note_end_beat > last_beat && note_end_beat <= program_beat
-                                };
-
-                                if should_end {
-                                    context.send_event(NoteEvent::NoteOff {
-                                        timing: 0,
-                                        voice_id: None,
-                                        channel: 0,
-                                        note: pitch,
-                                        velocity: 0.0,
-                                    });
-                                    state.active_notes.clear_playing(pitch);
-                                }
+                            // Sub-block B: the new program picks up exactly
+                            // where the old one left off, its own beat zero
+                            // aligned to the boundary sample.
+                            if pending.program.loaded && pending.program.length_beats > 0.0 {
+                                emit_program_block(
+                                    &pending.program,
+                                    0.0,
+                                    block_beats - (boundary - pos_beats),
+                                    boundary_sample as i32,
+                                    beats_per_sample,
+                                    buffer_len,
+                                    &self.humanize_cache,
+                                    &mut self.active_notes,
+                                    context,
+                                );
                             }
                         }
+                    } else {
+                        let program = self.program_buf.read(&mut self.program_reader_idx);
+                        if program.loaded && program.length_beats > 0.0 {
+                            let program_beat_start = pos_beats % program.length_beats;
+                            emit_program_block(
+                                program,
+                                program_beat_start,
+                                block_beats,
+                                0,
+                                beats_per_sample,
+                                buffer_len,
+                                &self.humanize_cache,
+                                &mut self.active_notes,
+                                context,
+                            );
+                        }
+                    }
+
+                    self.last_program_beat = pos_beats;
+                    self.expected_next_beat = pos_beats + block_beats;
+                }
+            }
+        } else {
+            // Transport stopped - send note-off for all active notes
+            flush_all_notes_off(&mut self.active_notes, context, 0);
+            self.last_program_beat = -1.0;
+            self.expected_next_beat = -1.0;
+        }
 
-                        state.last_program_beat = program_beat;
+        // Use try_borrow_mut to avoid panic if GUI is reading state
+        // If contention, skip this update - GUI will get next one
+        if let Ok(mut state) = self.state.try_borrow_mut() {
+            // Keep the emission path's humanize snapshot fresh without it ever
+            // needing to borrow `state` itself.
+            self.humanize_cache = state.humanize;
+            // Same reasoning for the pitch tracker's enabled/gate settings.
+            self.pitch_track_cache = state.pitch_track;
+            self.wasm_dsp_enabled = state.wasm_dsp.enabled;
+
+            // Tracks whether this block's event handling below touched
+            // `state.program`, so the triple buffer is only remirrored when
+            // there's actually something new for the audio thread to read.
+            let mut program_dirty = false;
+
+            // === Control Surface ===
+            // While a pad-grid controller is in control-surface mode, incoming
+            // notes are pad presses (step toggles), not performance/capture
+            // input - claim the event queue here instead of falling through.
+            if state.control_surface.config.enabled {
+                let config = state.control_surface.config;
+                while let Some(event) = context.next_event() {
+                    if let NoteEvent::NoteOn { note, .. } = event {
+                        control_surface::handle_pad_note_on(&mut state.program, &config, note);
+                        program_dirty = true;
                     }
                 }
-            } else if !transport.playing {
-                // Transport stopped - send note-off for all active notes
-                for pitch in 0u8..128 {
-                    if state.active_notes.is_playing(pitch) {
-                        context.send_event(NoteEvent::NoteOff {
-                            timing: 0,
-                            voice_id: None,
-                            channel: 0,
-                            note: pitch,
-                            velocity: 0.0,
-                        });
-                        state.active_notes.clear_playing(pitch);
+            } else
+            // === Live MIDI Capture ===
+            // Consumes incoming NoteOn/NoteOff from the host and, while armed,
+            // records them into the staged program (quantized, after count-in).
+            if state.recording {
+                if let Some(pos_beats) = transport.pos_beats() {
+                    state.record.arm(pos_beats);
+                }
+                let grid = state.record_quantize;
+                while let Some(event) = context.next_event() {
+                    match event {
+                        NoteEvent::NoteOn { note, velocity, .. } => {
+                            if let Some(pos_beats) = transport.pos_beats() {
+                                if state.record.is_capturing(pos_beats) {
+                                    capture::note_on(&mut state.record, note, velocity, pos_beats, grid);
+                                }
+                            }
+                        }
+                        NoteEvent::NoteOff { note, .. } => {
+                            if let Some(pos_beats) = transport.pos_beats() {
+                                if state.record.is_capturing(pos_beats) {
+                                    capture::note_off(&mut state.record, &mut state.program, note, pos_beats, grid);
+                                    program_dirty = true;
+                                }
+                            }
+                        }
+                        _ => {}
                     }
                 }
-                state.last_program_beat = -1.0;
+            } else {
+                state.record.disarm();
+                // Drain any input events so they don't pile up while not recording.
+                while context.next_event().is_some() {}
+            }
+
+            // Mirror any program edit made above into the audio thread's
+            // triple buffer. Best-effort: on the rare occasion this loses the
+            // race with a GUI or Gilligan-sync write, the edit simply shows up
+            // a block later instead of ever stalling this thread.
+            if program_dirty {
+                self.program_buf.try_write(|p| *p = state.program.clone());
             }
+
+            // === Control Surface LED Feedback ===
+            // Light active step cells (note-on velocity only: brightness
+            // follows the track color, no RGB), tick the playhead
+            // column, and mirror transport/loop state on the reserved pads.
+            if state.control_surface.config.enabled {
+                let playhead_beat = transport.pos_beats();
+                let track_color = self.track_info_buf.read(&mut self.track_info_reader_idx).color;
+                let loop_active = transport.loop_range_beats().is_some();
+                let updates = control_surface::led_updates(
+                    &mut state.control_surface,
+                    &state.program,
+                    playhead_beat,
+                    track_color,
+                    transport.playing,
+                    loop_active,
+                );
+                for &(note, velocity) in updates {
+                    context.send_event(NoteEvent::NoteOn {
+                        timing: 0,
+                        voice_id: None,
+                        channel: 0,
+                        note,
+                        velocity,
+                    });
+                }
+            }
+
+            // === Metronome Click ===
+            metronome::render(
+                &mut state.click,
+                &state.metronome,
+                buffer,
+                transport.pos_beats(),
+                transport.tempo,
+                transport.time_sig_numerator,
+                transport.sample_rate,
+                transport.playing,
+            );
+
+            // === DSP Load Meter ===
+            state.load_meter.record(process_start.elapsed(), buffer.samples(), transport.sample_rate);
         }
 
         ProcessStatus::Normal
     }
-
-    fn deactivate(&mut self) {
-        nih_log!("Skipper deactivated (id={})", self.instance_id);
-    }
 }
 
 impl ClapPlugin for Skipper {
@@ -0,0 +1,31 @@
+//! DSP-load tracking: each `process()` call's wall-clock time is measured
+//! against its real-time budget (`num_samples / sample_rate`), smoothed
+//! with an exponential moving average so the Info tab reading doesn't
+//! flicker block-to-block, alongside an unsmoothed peak for spotting
+//! transient spikes the average would hide.
+
+use std::time::Duration;
+
+const EMA_ALPHA: f32 = 0.2;
+
+/// Smoothed and peak DSP load, stored in `SharedState`.
+#[derive(Clone, Copy, Default)]
+pub struct LoadMeter {
+    pub smoothed_pct: f32,
+    pub peak_pct: f32,
+}
+
+impl LoadMeter {
+    /// Record one block's processing time against its real-time budget,
+    /// updating the smoothed and peak readings.
+    pub fn record(&mut self, elapsed: Duration, num_samples: usize, sample_rate: f32) {
+        if num_samples == 0 || sample_rate <= 0.0 {
+            return;
+        }
+        let budget_secs = num_samples as f32 / sample_rate;
+        let load_pct = (elapsed.as_secs_f32() / budget_secs) * 100.0;
+
+        self.smoothed_pct += EMA_ALPHA * (load_pct - self.smoothed_pct);
+        self.peak_pct = self.peak_pct.max(load_pct);
+    }
+}
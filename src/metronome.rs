@@ -0,0 +1,120 @@
+//! Built-in click track: a short enveloped sine burst on every beat
+//! boundary, with a higher/louder accent on beat 1 of the bar. Phase and
+//! envelope are derived purely from a per-click sample counter rather than
+//! an accumulated oscillator phase, so the click can never drift or jitter
+//! across buffers regardless of block size.
+
+use nih_plug::prelude::Buffer;
+
+const CLICK_FREQ_HZ: f32 = 1000.0;
+const ACCENT_FREQ_HZ: f32 = 1500.0;
+const CLICK_LEN_MS: f32 = 20.0;
+const ATTACK_MS: f32 = 5.0;
+const ACCENT_GAIN: f32 = 1.0;
+const BEAT_GAIN: f32 = 0.7;
+
+/// User-facing metronome settings, edited from the Live tab.
+#[derive(Clone, Copy)]
+pub struct MetronomeConfig {
+    pub enabled: bool,
+    pub volume: f32,
+}
+
+impl Default for MetronomeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.5,
+        }
+    }
+}
+
+/// Persistent click-synth state, stored in `SharedState`.
+#[derive(Clone, Copy, Default)]
+pub struct ClickState {
+    /// Samples into the current click, or `None` between clicks.
+    samples_elapsed: Option<u32>,
+    accent: bool,
+    /// Beat (floored) the last click was triggered for, so a click fires
+    /// exactly once per beat boundary even if it's re-checked mid-buffer.
+    last_beat_floor: Option<i64>,
+}
+
+impl ClickState {
+    fn trigger(&mut self, accent: bool) {
+        self.samples_elapsed = Some(0);
+        self.accent = accent;
+    }
+
+    /// Advance the click by one sample and return its contribution, or
+    /// `None` once the click has finished (or none is playing).
+    fn next_sample(&mut self, sample_rate: f32) -> Option<f32> {
+        let elapsed = self.samples_elapsed?;
+        let len_samples = (CLICK_LEN_MS / 1000.0 * sample_rate) as u32;
+        if elapsed >= len_samples {
+            self.samples_elapsed = None;
+            return None;
+        }
+
+        let attack_samples = ((ATTACK_MS / 1000.0 * sample_rate) as u32).max(1);
+        let envelope = if elapsed < attack_samples {
+            elapsed as f32 / attack_samples as f32
+        } else {
+            let decay_samples = len_samples.saturating_sub(attack_samples).max(1);
+            let decay_elapsed = elapsed - attack_samples;
+            (1.0 - decay_elapsed as f32 / decay_samples as f32).max(0.0)
+        };
+
+        let freq = if self.accent { ACCENT_FREQ_HZ } else { CLICK_FREQ_HZ };
+        let gain = if self.accent { ACCENT_GAIN } else { BEAT_GAIN };
+        let phase = 2.0 * std::f32::consts::PI * freq * (elapsed as f32 / sample_rate);
+
+        self.samples_elapsed = Some(elapsed + 1);
+        Some(phase.sin() * envelope * gain)
+    }
+}
+
+/// Mix the click track into `buffer`, sample-accurately triggering a new
+/// click on every beat boundary the block crosses.
+pub fn render(
+    click: &mut ClickState,
+    config: &MetronomeConfig,
+    buffer: &mut Buffer,
+    pos_beats: Option<f64>,
+    tempo: Option<f64>,
+    time_sig_numerator: Option<i32>,
+    sample_rate: f32,
+    playing: bool,
+) {
+    if !config.enabled || !playing {
+        click.last_beat_floor = None;
+        return;
+    }
+    let (Some(pos_beats), Some(tempo)) = (pos_beats, tempo) else {
+        return;
+    };
+    if tempo <= 0.0 || sample_rate <= 0.0 {
+        return;
+    }
+
+    let beats_per_numerator = time_sig_numerator.unwrap_or(4).max(1) as i64;
+    let beats_per_sample = tempo / 60.0 / sample_rate as f64;
+
+    for (i, channel_samples) in buffer.iter_samples().enumerate() {
+        let beat = pos_beats + i as f64 * beats_per_sample;
+        let beat_floor = beat.floor() as i64;
+
+        if click.last_beat_floor != Some(beat_floor) {
+            click.last_beat_floor = Some(beat_floor);
+            let beat_in_bar = beat_floor.rem_euclid(beats_per_numerator);
+            click.trigger(beat_in_bar == 0);
+        }
+
+        if let Some(value) = click.next_sample(sample_rate) {
+            let scaled = value * config.volume;
+            for sample in channel_samples {
+                *sample += scaled;
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Cross-session persistence of the plugin's editable settings.
+//!
+//! `nih_export_clap!` generates the plugin's whole vtable, including the
+//! real `clap_plugin_state` extension - `save`/`load` against a host-given
+//! `clap_ostream`/`clap_istream`, exactly like CLAP specifies. The hook
+//! nih_plug gives a plugin crate for filling those in is a `#[persist]`
+//! field on a `Params` struct (already used here for `editor_state`): nih_plug
+//! serializes every `#[persist]` field and is what answers the host's
+//! `save`/`load` calls with that blob, so this module never touches
+//! `clap_ostream`/`clap_istream` directly and doesn't need to - see
+//! `tests/persistence_state_roundtrip.rs`, which drives the real extension
+//! end-to-end and confirms the blob this module produces survives it.
+//!
+//! This module owns the format stored behind that field: a TOML document
+//! (a schema version plus the current value of every setting worth
+//! restoring), serialized with [`serialize_settings`] and applied back with
+//! [`apply_settings`]. `apply_settings` is deliberately defensive - unknown
+//! keys are ignored, out-of-range values are clamped, and a missing or older
+//! `schema_version` just means the fields introduced since are left at
+//! their defaults - so a state chunk saved by an older or newer build of
+//! this plugin never corrupts the one loading it.
+
+use crate::{capture, control_surface, humanize, metronome, pitch_track, LaunchQuantization, SharedState};
+
+/// Bumped whenever a field is added, removed, or reinterpreted below.
+const SCHEMA_VERSION: i64 = 1;
+
+/// Serialize the settings a user would want restored across host sessions
+/// (the performance/generator knobs on the Live and Program tabs) to a TOML
+/// document. Program content itself isn't included here - that round-trips
+/// through Gilligan (see `sync`), not plugin state.
+pub fn serialize_settings(shared: &SharedState) -> String {
+    let mut table = toml::Table::new();
+    table.insert("schema_version".to_string(), toml::Value::Integer(SCHEMA_VERSION));
+
+    table.insert("launch_quantization".to_string(), toml::Value::String(launch_quantization_tag(shared.launch_quantization).to_string()));
+    if let LaunchQuantization::Bars(n) = shared.launch_quantization {
+        table.insert("launch_quantization_bars".to_string(), toml::Value::Integer(n as i64));
+    }
+
+    table.insert("record_quantize".to_string(), toml::Value::String(record_quantize_tag(shared.record_quantize).to_string()));
+
+    table.insert("humanize_swing_amount".to_string(), toml::Value::Float(shared.humanize.swing_amount as f64));
+    table.insert("humanize_max_swing_beats".to_string(), toml::Value::Float(shared.humanize.max_swing_beats));
+    table.insert("humanize_timing_jitter_beats".to_string(), toml::Value::Float(shared.humanize.timing_jitter_beats));
+    table.insert("humanize_velocity_jitter".to_string(), toml::Value::Float(shared.humanize.velocity_jitter as f64));
+
+    table.insert("metronome_enabled".to_string(), toml::Value::Boolean(shared.metronome.enabled));
+    table.insert("metronome_volume".to_string(), toml::Value::Float(shared.metronome.volume as f64));
+
+    table.insert("control_surface_enabled".to_string(), toml::Value::Boolean(shared.control_surface.config.enabled));
+    table.insert("control_surface_base_note".to_string(), toml::Value::Integer(shared.control_surface.config.base_note as i64));
+
+    table.insert("pitch_track_enabled".to_string(), toml::Value::Boolean(shared.pitch_track.enabled));
+    table.insert("pitch_track_gate_threshold".to_string(), toml::Value::Float(shared.pitch_track.gate_threshold as f64));
+
+    toml::Value::Table(table).to_string()
+}
+
+/// Parse `toml_text` and apply whatever it contains onto `shared`, leaving
+/// every field at its current value if the document is empty, unparsable,
+/// or simply doesn't mention that field. Out-of-range values are clamped
+/// rather than rejected outright, so a slightly-off hand-edited state file
+/// degrades gracefully instead of losing the whole load.
+pub fn apply_settings(shared: &mut SharedState, toml_text: &str) {
+    let Ok(root) = toml_text.parse::<toml::Value>() else { return };
+    let Some(table) = root.as_table() else { return };
+
+    // `schema_version` only gates which fields we *expect* to find - it's
+    // never a reason to refuse the rest of the document. A missing or older
+    // version just means fields added since were never written, so they
+    // fall through to whatever `shared` already held (its own defaults on a
+    // fresh instance).
+    let _version = table.get("schema_version").and_then(toml::Value::as_integer).unwrap_or(0);
+
+    if let Some(tag) = table.get("launch_quantization").and_then(toml::Value::as_str) {
+        let bars = table.get("launch_quantization_bars").and_then(toml::Value::as_integer).unwrap_or(2).clamp(1, 64) as u32;
+        if let Some(q) = launch_quantization_from_tag(tag, bars) {
+            shared.launch_quantization = q;
+        }
+    }
+
+    if let Some(tag) = table.get("record_quantize").and_then(toml::Value::as_str) {
+        if let Some(grid) = record_quantize_from_tag(tag) {
+            shared.record_quantize = grid;
+        }
+    }
+
+    if let Some(v) = table.get("humanize_swing_amount").and_then(toml::Value::as_float) {
+        shared.humanize.swing_amount = (v as f32).clamp(0.0, 1.0);
+    }
+    if let Some(v) = table.get("humanize_max_swing_beats").and_then(toml::Value::as_float) {
+        shared.humanize.max_swing_beats = v.clamp(0.0, 0.25);
+    }
+    if let Some(v) = table.get("humanize_timing_jitter_beats").and_then(toml::Value::as_float) {
+        shared.humanize.timing_jitter_beats = v.clamp(0.0, 0.1);
+    }
+    if let Some(v) = table.get("humanize_velocity_jitter").and_then(toml::Value::as_float) {
+        shared.humanize.velocity_jitter = (v as f32).clamp(0.0, 1.0);
+    }
+
+    if let Some(v) = table.get("metronome_enabled").and_then(toml::Value::as_bool) {
+        shared.metronome.enabled = v;
+    }
+    if let Some(v) = table.get("metronome_volume").and_then(toml::Value::as_float) {
+        shared.metronome.volume = (v as f32).clamp(0.0, 1.0);
+    }
+
+    if let Some(v) = table.get("control_surface_enabled").and_then(toml::Value::as_bool) {
+        shared.control_surface.config.enabled = v;
+    }
+    if let Some(v) = table.get("control_surface_base_note").and_then(toml::Value::as_integer) {
+        shared.control_surface.config.base_note = v.clamp(0, 127 - control_surface::TOTAL_PADS as i64) as u8;
+    }
+
+    if let Some(v) = table.get("pitch_track_enabled").and_then(toml::Value::as_bool) {
+        shared.pitch_track.enabled = v;
+    }
+    if let Some(v) = table.get("pitch_track_gate_threshold").and_then(toml::Value::as_float) {
+        shared.pitch_track.gate_threshold = (v as f32).clamp(0.0, 0.2);
+    }
+}
+
+fn launch_quantization_tag(q: LaunchQuantization) -> &'static str {
+    match q {
+        LaunchQuantization::Immediate => "immediate",
+        LaunchQuantization::NextBeat => "next_beat",
+        LaunchQuantization::NextBar => "next_bar",
+        LaunchQuantization::Bars(_) => "bars",
+    }
+}
+
+fn launch_quantization_from_tag(tag: &str, bars: u32) -> Option<LaunchQuantization> {
+    match tag {
+        "immediate" => Some(LaunchQuantization::Immediate),
+        "next_beat" => Some(LaunchQuantization::NextBeat),
+        "next_bar" => Some(LaunchQuantization::NextBar),
+        "bars" => Some(LaunchQuantization::Bars(bars)),
+        _ => None,
+    }
+}
+
+fn record_quantize_tag(grid: capture::QuantizeGrid) -> &'static str {
+    match grid {
+        capture::QuantizeGrid::Off => "off",
+        capture::QuantizeGrid::Quarter => "quarter",
+        capture::QuantizeGrid::Eighth => "eighth",
+        capture::QuantizeGrid::Sixteenth => "sixteenth",
+    }
+}
+
+fn record_quantize_from_tag(tag: &str) -> Option<capture::QuantizeGrid> {
+    match tag {
+        "off" => Some(capture::QuantizeGrid::Off),
+        "quarter" => Some(capture::QuantizeGrid::Quarter),
+        "eighth" => Some(capture::QuantizeGrid::Eighth),
+        "sixteenth" => Some(capture::QuantizeGrid::Sixteenth),
+        _ => None,
+    }
+}
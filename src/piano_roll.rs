@@ -0,0 +1,482 @@
+//! Interactive piano-roll editor for the Program tab: a time/pitch grid
+//! where click-drag creates a note, dragging a note's body moves it,
+//! dragging its right edge resizes it, and right-click deletes it.
+//!
+//! Edits mutate `StagedProgram.notes` directly - there is no round-trip
+//! through Gilligan, so the plugin plays back exactly what's drawn here.
+//!
+//! Shift-dragging an empty area of the grid rubber-band selects the notes
+//! it overlaps (shift-click toggles a single note) for the batch transpose/
+//! nudge/quantize operations below the grid.
+
+use crate::{ProgramNote, StagedProgram, MAX_NOTES};
+use nih_plug_egui::egui;
+use std::collections::HashSet;
+
+/// What the current drag gesture is doing, captured on `drag_started`.
+#[derive(Clone)]
+enum DragMode {
+    CreatingNote { start_beat: f64, pitch: u8 },
+    MovingNote { grab_offset_beats: f64, originals: Vec<(usize, f64, u8)> },
+    ResizingNote { index: usize },
+    Selecting { anchor: egui::Pos2, additive: bool },
+}
+
+/// Persistent piano-roll UI state, stored in `SharedState`.
+pub struct PianoRollState {
+    drag: Option<DragMode>,
+    pitch_low: u8,
+    pitch_high: u8,
+    /// Indices into `program.notes` currently selected for batch edits.
+    selected: HashSet<usize>,
+    /// Grid division used by the nudge/quantize toolbar below the grid.
+    edit_grid: NoteGrid,
+}
+
+impl Default for PianoRollState {
+    fn default() -> Self {
+        Self {
+            drag: None,
+            pitch_low: 36,  // C2
+            pitch_high: 84, // C6
+            selected: HashSet::new(),
+            edit_grid: NoteGrid::Sixteenth,
+        }
+    }
+}
+
+/// Grid division offered by the nudge/quantize toolbar.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NoteGrid {
+    Quarter,
+    Eighth,
+    Sixteenth,
+    Triplet,
+}
+
+impl NoteGrid {
+    fn beats(self) -> f64 {
+        match self {
+            NoteGrid::Quarter => 1.0,
+            NoteGrid::Eighth => 0.5,
+            NoteGrid::Sixteenth => 0.25,
+            NoteGrid::Triplet => 1.0 / 3.0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            NoteGrid::Quarter => "1/4",
+            NoteGrid::Eighth => "1/8",
+            NoteGrid::Sixteenth => "1/16",
+            NoteGrid::Triplet => "1/3T",
+        }
+    }
+}
+
+/// Pixels from a note's right edge within which a drag grabs the resize handle.
+const EDGE_GRAB_PX: f32 = 6.0;
+/// Minimum note length, in beats, enforced by create/resize gestures.
+const MIN_NOTE_LEN_BEATS: f64 = 1.0 / 16.0;
+
+/// Render the piano-roll grid and apply any in-progress edit gesture.
+/// `playhead_beat` is the transport's absolute beat position (wrapped
+/// against `program.length_beats` internally) for the moving playhead line.
+pub fn render(ui: &mut egui::Ui, program: &mut StagedProgram, state: &mut PianoRollState, playhead_beat: Option<f64>) {
+    let pitch_count = (state.pitch_high - state.pitch_low + 1) as usize;
+    let row_height = 14.0f32;
+    let height = row_height * pitch_count as f32;
+    let width = ui.available_width().max(200.0);
+
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::click_and_drag());
+    let length_beats = program.length_beats.max(1.0);
+
+    let beat_to_x = |beat: f64| rect.left() + (beat / length_beats) as f32 * rect.width();
+    let x_to_beat = |x: f32| (((x - rect.left()) / rect.width()).max(0.0) as f64 * length_beats).max(0.0);
+    let pitch_to_y = |pitch: u8| rect.top() + (state.pitch_high.saturating_sub(pitch)) as f32 * row_height;
+    let y_to_pitch = |y: f32| -> u8 {
+        let row = ((y - rect.top()) / row_height).floor() as i32;
+        (state.pitch_high as i32 - row).clamp(state.pitch_low as i32, state.pitch_high as i32) as u8
+    };
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+    // Beat/bar grid lines, bars under the program's own time signature.
+    let beats_per_bar = program.beats_per_bar();
+    let mut beat = 0.0;
+    while beat <= length_beats {
+        let x = beat_to_x(beat);
+        let bar_pos = beat / beats_per_bar;
+        let is_bar = (bar_pos - bar_pos.round()).abs() < 1e-9;
+        let color = if is_bar { egui::Color32::from_gray(90) } else { egui::Color32::from_gray(45) };
+        painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], (1.0, color));
+        beat += 1.0;
+    }
+
+    // Existing notes, gathered once so hit-testing doesn't re-walk the array.
+    let note_rects: Vec<(usize, egui::Rect)> = (0..program.note_count)
+        .filter(|&i| program.notes[i].active)
+        .map(|i| {
+            let n = program.notes[i];
+            let x0 = beat_to_x(n.start_beat);
+            let x1 = beat_to_x(n.start_beat + n.length_beats);
+            let y = pitch_to_y(n.pitch);
+            (i, egui::Rect::from_min_max(egui::pos2(x0, y), egui::pos2(x1, y + row_height - 1.0)))
+        })
+        .collect();
+
+    for (index, r) in &note_rects {
+        let color = if state.selected.contains(index) {
+            egui::Color32::from_rgb(255, 200, 90)
+        } else {
+            egui::Color32::from_rgb(90, 180, 255)
+        };
+        painter.rect_filled(*r, 2.0, color);
+    }
+
+    if let Some(beat) = playhead_beat {
+        let x = beat_to_x(beat.rem_euclid(length_beats));
+        painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], (2.0, egui::Color32::from_rgb(100, 255, 100)));
+    }
+
+    let shift_held = ui.input(|i| i.modifiers.shift);
+
+    if response.drag_started() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            if let Some(&(index, r)) = note_rects.iter().find(|(_, r)| r.contains(pos)) {
+                if shift_held {
+                    // Shift-click toggles selection without starting a move/resize.
+                    if !state.selected.remove(&index) {
+                        state.selected.insert(index);
+                    }
+                } else if (r.right() - pos.x).abs() <= EDGE_GRAB_PX {
+                    state.drag = Some(DragMode::ResizingNote { index });
+                } else {
+                    if !state.selected.contains(&index) {
+                        state.selected.clear();
+                        state.selected.insert(index);
+                    }
+                    let grab_offset_beats = x_to_beat(pos.x) - program.notes[index].start_beat;
+                    let originals = state
+                        .selected
+                        .iter()
+                        .map(|&i| (i, program.notes[i].start_beat, program.notes[i].pitch))
+                        .collect();
+                    state.drag = Some(DragMode::MovingNote { grab_offset_beats, originals });
+                }
+            } else if shift_held {
+                state.drag = Some(DragMode::Selecting { anchor: pos, additive: true });
+            } else {
+                state.selected.clear();
+                let start_beat = x_to_beat(pos.x);
+                let pitch = y_to_pitch(pos.y);
+                state.drag = Some(DragMode::CreatingNote { start_beat, pitch });
+            }
+        }
+    }
+
+    if response.dragged() {
+        if let (Some(pos), Some(mode)) = (response.interact_pointer_pos(), state.drag.clone()) {
+            match mode {
+                DragMode::CreatingNote { start_beat, pitch } => {
+                    let end_beat = x_to_beat(pos.x).max(start_beat + MIN_NOTE_LEN_BEATS);
+                    let x0 = beat_to_x(start_beat);
+                    let x1 = beat_to_x(end_beat);
+                    let y = pitch_to_y(pitch);
+                    let preview = egui::Rect::from_min_max(egui::pos2(x0, y), egui::pos2(x1, y + row_height - 1.0));
+                    painter.rect_stroke(preview, 2.0, (1.5, egui::Color32::WHITE));
+                }
+                DragMode::MovingNote { grab_offset_beats, originals } => {
+                    let new_anchor_start = (x_to_beat(pos.x) - grab_offset_beats).max(0.0);
+                    let new_anchor_pitch = y_to_pitch(pos.y) as i32;
+                    if let Some(&(_, anchor_start, anchor_pitch)) = originals.first() {
+                        let delta_beat = new_anchor_start - anchor_start;
+                        let delta_pitch = new_anchor_pitch - anchor_pitch as i32;
+                        for &(index, start, pitch) in &originals {
+                            if let Some(note) = program.notes.get_mut(index) {
+                                note.start_beat = (start + delta_beat).max(0.0);
+                                note.pitch = (pitch as i32 + delta_pitch).clamp(0, 127) as u8;
+                            }
+                        }
+                    }
+                }
+                DragMode::ResizingNote { index } => {
+                    if let Some(note) = program.notes.get_mut(index) {
+                        note.length_beats = (x_to_beat(pos.x) - note.start_beat).max(MIN_NOTE_LEN_BEATS);
+                    }
+                }
+                DragMode::Selecting { anchor, .. } => {
+                    let select_rect = egui::Rect::from_two_pos(anchor, pos);
+                    painter.rect_stroke(select_rect, 0.0, (1.0, egui::Color32::WHITE));
+                }
+            }
+        }
+    }
+
+    if response.drag_released() {
+        match state.drag.take() {
+            Some(DragMode::CreatingNote { start_beat, pitch }) => {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    let end_beat = x_to_beat(pos.x).max(start_beat + MIN_NOTE_LEN_BEATS);
+                    add_note(program, pitch, start_beat, end_beat - start_beat);
+                }
+            }
+            Some(DragMode::Selecting { anchor, additive }) => {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    let select_rect = egui::Rect::from_two_pos(anchor, pos);
+                    if !additive {
+                        state.selected.clear();
+                    }
+                    for &(index, r) in &note_rects {
+                        if select_rect.intersects(r) {
+                            state.selected.insert(index);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if response.secondary_clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            if let Some(&(index, _)) = note_rects.iter().find(|(_, r)| r.contains(pos)) {
+                program.notes[index].active = false;
+                state.selected.remove(&index);
+            }
+        }
+    }
+}
+
+/// Insert a new note into the first inactive slot (reusing a deleted note's
+/// slot before growing `note_count`), dropping it silently past `MAX_NOTES`.
+fn add_note(program: &mut StagedProgram, pitch: u8, start_beat: f64, length_beats: f64) {
+    let slot = (0..program.note_count)
+        .find(|&i| !program.notes[i].active)
+        .or_else(|| {
+            if program.note_count < MAX_NOTES {
+                let i = program.note_count;
+                program.note_count += 1;
+                Some(i)
+            } else {
+                None
+            }
+        });
+
+    if let Some(i) = slot {
+        program.notes[i] = ProgramNote {
+            pitch,
+            velocity: 0.8,
+            start_beat,
+            length_beats,
+            active: true,
+            channel: 0,
+            expression: None,
+        };
+        program.version = program.version.wrapping_add(1);
+        program.loaded = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_note_fills_first_inactive_slot_before_growing() {
+        let mut program = StagedProgram::default();
+        add_note(&mut program, 60, 0.0, 1.0);
+        add_note(&mut program, 62, 1.0, 1.0);
+        assert_eq!(program.note_count, 2);
+
+        program.notes[0].active = false;
+        add_note(&mut program, 64, 2.0, 1.0);
+        // Reuses slot 0 rather than appending a third slot.
+        assert_eq!(program.note_count, 2);
+        assert_eq!(program.notes[0].pitch, 64);
+        assert!(program.notes[0].active);
+    }
+
+    #[test]
+    fn add_note_drops_past_max_notes() {
+        let mut program = StagedProgram::default();
+        program.note_count = MAX_NOTES;
+        for i in 0..MAX_NOTES {
+            program.notes[i].active = true;
+        }
+        add_note(&mut program, 60, 0.0, 1.0);
+        assert_eq!(program.note_count, MAX_NOTES);
+    }
+
+    #[test]
+    fn add_note_marks_program_loaded_and_bumps_version() {
+        let mut program = StagedProgram::default();
+        let version_before = program.version;
+        add_note(&mut program, 60, 0.0, 1.0);
+        assert!(program.loaded);
+        assert_eq!(program.version, version_before.wrapping_add(1));
+    }
+}
+
+/// Shift all selected notes' pitches by `semitones`, clamped to 0-127.
+pub fn transpose_selected(program: &mut StagedProgram, selected: &HashSet<usize>, semitones: i32) {
+    for &i in selected {
+        if let Some(note) = program.notes.get_mut(i) {
+            note.pitch = (note.pitch as i32 + semitones).clamp(0, 127) as u8;
+        }
+    }
+    program.version = program.version.wrapping_add(1);
+}
+
+/// Nudge all selected notes' `start_beat` by one `grid` division, never
+/// moving a note before beat 0.
+pub fn nudge_selected(program: &mut StagedProgram, selected: &HashSet<usize>, grid: NoteGrid, direction: i32) {
+    let delta = grid.beats() * direction as f64;
+    for &i in selected {
+        if let Some(note) = program.notes.get_mut(i) {
+            note.start_beat = (note.start_beat + delta).max(0.0);
+        }
+    }
+    program.version = program.version.wrapping_add(1);
+}
+
+/// Snap all selected notes' `start_beat` and `length_beats` to the nearest
+/// `grid` division. Notes quantized past `program.length_beats` are
+/// clamped back inside `[0, length_beats)` rather than dropped.
+pub fn quantize_selected(program: &mut StagedProgram, selected: &HashSet<usize>, grid: NoteGrid) {
+    let g = grid.beats();
+    let total = program.length_beats.max(g);
+    for &i in selected {
+        if let Some(note) = program.notes.get_mut(i) {
+            let start = (note.start_beat / g).round() * g;
+            let end = ((note.start_beat + note.length_beats) / g).round() * g;
+            let start = start.min(total - g).max(0.0);
+            let length = (end - start).max(g).min(total - start);
+            note.start_beat = start;
+            note.length_beats = length;
+        }
+    }
+    program.version = program.version.wrapping_add(1);
+}
+
+#[cfg(test)]
+mod selection_tests {
+    use super::*;
+
+    fn note_at(start_beat: f64, length_beats: f64) -> ProgramNote {
+        ProgramNote {
+            pitch: 60,
+            velocity: 0.8,
+            start_beat,
+            length_beats,
+            active: true,
+            channel: 0,
+            expression: None,
+        }
+    }
+
+    #[test]
+    fn transpose_selected_shifts_and_clamps_pitch() {
+        let mut program = StagedProgram::default();
+        program.note_count = 2;
+        program.notes[0] = note_at(0.0, 1.0);
+        program.notes[0].pitch = 120;
+        program.notes[1] = note_at(1.0, 1.0);
+        program.notes[1].pitch = 60;
+
+        let selected: HashSet<usize> = [0, 1].into_iter().collect();
+        transpose_selected(&mut program, &selected, 12);
+        assert_eq!(program.notes[0].pitch, 127); // clamped instead of overflowing u8
+        assert_eq!(program.notes[1].pitch, 72);
+    }
+
+    #[test]
+    fn nudge_selected_moves_start_beat_and_floors_at_zero() {
+        let mut program = StagedProgram::default();
+        program.note_count = 1;
+        program.notes[0] = note_at(0.1, 1.0);
+
+        let selected: HashSet<usize> = [0].into_iter().collect();
+        nudge_selected(&mut program, &selected, NoteGrid::Quarter, -1);
+        assert_eq!(program.notes[0].start_beat, 0.0);
+
+        nudge_selected(&mut program, &selected, NoteGrid::Eighth, 1);
+        assert_eq!(program.notes[0].start_beat, 0.5);
+    }
+
+    #[test]
+    fn quantize_selected_snaps_to_grid_and_clamps_within_length() {
+        let mut program = StagedProgram::default();
+        program.length_beats = 4.0;
+        program.note_count = 1;
+        program.notes[0] = note_at(0.9, 0.3);
+
+        let selected: HashSet<usize> = [0].into_iter().collect();
+        quantize_selected(&mut program, &selected, NoteGrid::Quarter);
+        assert_eq!(program.notes[0].start_beat, 1.0);
+        assert_eq!(program.notes[0].length_beats, 1.0);
+    }
+
+    #[test]
+    fn quantize_selected_keeps_note_inside_program_length() {
+        let mut program = StagedProgram::default();
+        program.length_beats = 4.0;
+        program.note_count = 1;
+        program.notes[0] = note_at(3.9, 0.2);
+
+        let selected: HashSet<usize> = [0].into_iter().collect();
+        quantize_selected(&mut program, &selected, NoteGrid::Quarter);
+        assert!(program.notes[0].start_beat + program.notes[0].length_beats <= 4.0 + 1e-9);
+        assert!(program.notes[0].start_beat >= 0.0);
+    }
+}
+
+/// Render the batch-edit toolbar (transpose / nudge / quantize) acting on
+/// `state.selected`. A no-op when nothing is selected.
+pub fn render_selection_toolbar(ui: &mut egui::Ui, program: &mut StagedProgram, state: &mut PianoRollState) {
+    egui::CollapsingHeader::new("Selection")
+        .default_open(false)
+        .show(ui, |ui| {
+            ui.label(format!("{} note(s) selected", state.selected.len()));
+            if state.selected.is_empty() {
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Transpose");
+                if ui.button("-12").clicked() {
+                    transpose_selected(program, &state.selected, -12);
+                }
+                if ui.button("-1").clicked() {
+                    transpose_selected(program, &state.selected, -1);
+                }
+                if ui.button("+1").clicked() {
+                    transpose_selected(program, &state.selected, 1);
+                }
+                if ui.button("+12").clicked() {
+                    transpose_selected(program, &state.selected, 12);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Grid");
+                for grid in [NoteGrid::Quarter, NoteGrid::Eighth, NoteGrid::Sixteenth, NoteGrid::Triplet] {
+                    ui.selectable_value(&mut state.edit_grid, grid, grid.label());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Nudge");
+                if ui.button("<").clicked() {
+                    nudge_selected(program, &state.selected, state.edit_grid, -1);
+                }
+                if ui.button(">").clicked() {
+                    nudge_selected(program, &state.selected, state.edit_grid, 1);
+                }
+                if ui.button("Quantize").clicked() {
+                    quantize_selected(program, &state.selected, state.edit_grid);
+                }
+            });
+        });
+}
@@ -0,0 +1,348 @@
+//! Optional audio-to-MIDI mode: tracks the fundamental pitch of a
+//! monophonic input signal and emits NoteOn/NoteOff for it, turning Skipper
+//! from a purely transport-driven sequencer into a live pitch-to-MIDI
+//! converter (for tracking a voice or instrument).
+//!
+//! Detection is time-domain (YIN/MPM-style): incoming samples are mixed
+//! down to mono and accumulated into a rolling ~2048-sample window with
+//! roughly 1/3 overlap between analyses. Each window's normalized
+//! difference function is searched for the first lag below an absolute
+//! threshold, refined to the enclosing local minimum, then parabolically
+//! interpolated for sub-sample accuracy before being converted to a MIDI
+//! note. All ring-buffer and scratch allocation happens once in `prepare`
+//! (called from `initialize()`) - `process_block` never allocates.
+
+use nih_plug::prelude::{Buffer, NoteEvent, ProcessContext};
+
+use crate::Skipper;
+
+/// Analysis window length in samples, independent of sample rate.
+const WINDOW_SIZE: usize = 2048;
+/// Samples advanced between analyses (~1/3 overlap, i.e. 2/3 of a window).
+const HOP_SIZE: usize = WINDOW_SIZE - WINDOW_SIZE / 3;
+/// Lowest pitch searched for, bounding the longest lag in the search.
+const MIN_FREQUENCY_HZ: f32 = 60.0;
+/// Highest pitch searched for, bounding the shortest lag in the search.
+const MAX_FREQUENCY_HZ: f32 = 1200.0;
+/// YIN absolute threshold: a candidate lag's normalized difference must
+/// drop below this to be accepted as the fundamental.
+const YIN_THRESHOLD: f32 = 0.1;
+/// MIDI channel emitted notes are sent on.
+const CHANNEL: u8 = 0;
+
+/// User-facing audio-to-MIDI settings, edited from the Live tab.
+#[derive(Clone, Copy)]
+pub struct PitchTrackConfig {
+    pub enabled: bool,
+    /// RMS level below which a window is treated as silence and any held
+    /// note is released.
+    pub gate_threshold: f32,
+}
+
+impl Default for PitchTrackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gate_threshold: 0.02,
+        }
+    }
+}
+
+/// Audio-thread-only pitch-tracking bookkeeping: the rolling input window,
+/// reused analysis scratch, and the currently-held note (if any). Lives on
+/// `Skipper` itself, never in `SharedState` - see `ActiveNotes` for the same
+/// pattern with program playback.
+pub struct PitchTrackState {
+    /// Circular buffer of the most recent `WINDOW_SIZE` input samples.
+    ring: Vec<f32>,
+    /// Next index in `ring` to write (the oldest sample once `filled` saturates).
+    write_pos: usize,
+    /// Valid samples written so far, capped at `WINDOW_SIZE`.
+    filled: usize,
+    /// Samples accumulated since the last analysis, to trigger every `HOP_SIZE`.
+    since_last_analysis: usize,
+    /// Contiguous, chronologically-ordered copy of the current window,
+    /// reused every analysis instead of being reallocated.
+    window_scratch: Vec<f32>,
+    /// Difference-function scratch, one entry per candidate lag (including 0).
+    diff_scratch: Vec<f32>,
+    /// Shortest and longest lag searched, in samples - depend on sample rate
+    /// so they're computed once in `prepare`.
+    tau_min: usize,
+    tau_max: usize,
+    /// MIDI pitch currently being sounded, if any.
+    held_note: Option<u8>,
+}
+
+impl PitchTrackState {
+    pub fn new() -> Self {
+        Self {
+            ring: Vec::new(),
+            write_pos: 0,
+            filled: 0,
+            since_last_analysis: 0,
+            window_scratch: Vec::new(),
+            diff_scratch: Vec::new(),
+            tau_min: 0,
+            tau_max: 0,
+            held_note: None,
+        }
+    }
+
+    /// Allocate the ring buffer and analysis scratch for `sample_rate`.
+    /// Called once from `initialize()` - `process_block` never allocates.
+    pub fn prepare(&mut self, sample_rate: f32) {
+        self.ring = vec![0.0; WINDOW_SIZE];
+        self.window_scratch = vec![0.0; WINDOW_SIZE];
+        self.tau_min = ((sample_rate / MAX_FREQUENCY_HZ).round() as usize).max(1);
+        self.tau_max = ((sample_rate / MIN_FREQUENCY_HZ).round() as usize).min(WINDOW_SIZE / 2);
+        self.diff_scratch = vec![0.0; self.tau_max + 1];
+        self.write_pos = 0;
+        self.filled = 0;
+        self.since_last_analysis = 0;
+        self.held_note = None;
+    }
+}
+
+impl Default for PitchTrackState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Root-mean-square level of `window`.
+fn rms(window: &[f32]) -> f32 {
+    if window.is_empty() {
+        return 0.0;
+    }
+    let sum_sq: f32 = window.iter().map(|&x| x * x).sum();
+    (sum_sq / window.len() as f32).sqrt()
+}
+
+/// `d(tau) = sum_n (x[n] - x[n+tau])^2` for `tau` in `0..=max_lag`.
+fn difference_function(window: &[f32], diff: &mut [f32]) {
+    let len = window.len();
+    diff[0] = 0.0;
+    for tau in 1..diff.len() {
+        let mut sum = 0.0f32;
+        for n in 0..(len - tau) {
+            let delta = window[n] - window[n + tau];
+            sum += delta * delta;
+        }
+        diff[tau] = sum;
+    }
+}
+
+/// In place, turn `d(tau)` into YIN's cumulative mean normalized difference
+/// `d'(tau)`, so smaller lags aren't favored just for having a smaller raw sum.
+fn cumulative_mean_normalize(diff: &mut [f32]) {
+    diff[0] = 1.0;
+    let mut running_sum = 0.0f32;
+    for tau in 1..diff.len() {
+        running_sum += diff[tau];
+        diff[tau] = if running_sum > 0.0 {
+            diff[tau] * tau as f32 / running_sum
+        } else {
+            1.0
+        };
+    }
+}
+
+/// First lag at or past `tau_min` whose normalized difference drops below
+/// `YIN_THRESHOLD`, walked forward to the enclosing local minimum (the dip
+/// usually keeps falling past the threshold crossing) so the later
+/// parabolic interpolation refines an actual minimum. `None` if no lag in
+/// `tau_min..=tau_max` qualifies.
+fn find_pitch_lag(diff: &[f32], tau_min: usize, tau_max: usize) -> Option<usize> {
+    let mut tau = tau_min;
+    while tau <= tau_max {
+        if diff[tau] < YIN_THRESHOLD {
+            while tau + 1 <= tau_max && diff[tau + 1] < diff[tau] {
+                tau += 1;
+            }
+            return Some(tau);
+        }
+        tau += 1;
+    }
+    None
+}
+
+/// Refine an integer lag to sub-sample accuracy by fitting a parabola
+/// through its neighbors in the difference function.
+fn parabolic_interpolate(diff: &[f32], tau: usize) -> f64 {
+    if tau == 0 || tau + 1 >= diff.len() {
+        return tau as f64;
+    }
+    let s0 = diff[tau - 1] as f64;
+    let s1 = diff[tau] as f64;
+    let s2 = diff[tau + 1] as f64;
+    let denom = s0 - 2.0 * s1 + s2;
+    if denom.abs() < 1e-12 {
+        tau as f64
+    } else {
+        tau as f64 + 0.5 * (s0 - s2) / denom
+    }
+}
+
+/// Detect the window's fundamental frequency in Hz, or `None` if no lag in
+/// range ever drops below the YIN threshold (inharmonic/noisy/silent input).
+fn detect_frequency(state: &mut PitchTrackState, sample_rate: f32) -> Option<f64> {
+    difference_function(&state.window_scratch, &mut state.diff_scratch);
+    cumulative_mean_normalize(&mut state.diff_scratch);
+    let tau = find_pitch_lag(&state.diff_scratch, state.tau_min, state.tau_max)?;
+    let refined_tau = parabolic_interpolate(&state.diff_scratch, tau);
+    if refined_tau <= 0.0 {
+        return None;
+    }
+    Some(sample_rate as f64 / refined_tau)
+}
+
+/// Convert a frequency in Hz to the nearest MIDI pitch.
+fn frequency_to_midi_note(freq_hz: f64) -> u8 {
+    (69.0 + 12.0 * (freq_hz / 440.0).log2()).round().clamp(0.0, 127.0) as u8
+}
+
+/// Map a window's RMS to a note velocity, loud enough to register on a
+/// gated signal without instantly pinning at max.
+fn rms_to_velocity(rms: f32) -> f32 {
+    (rms * 4.0).clamp(0.05, 1.0)
+}
+
+/// Downmix, accumulate, and analyze one block's input audio, emitting
+/// NoteOn/NoteOff for the tracked pitch at the exact sample each analysis
+/// window's hop boundary lands on.
+pub fn process_block(
+    state: &mut PitchTrackState,
+    config: &PitchTrackConfig,
+    sample_rate: f32,
+    buffer: &mut Buffer,
+    context: &mut impl ProcessContext<Skipper>,
+) {
+    if state.ring.is_empty() {
+        // `prepare` hasn't run yet (sample rate not known) - nothing to do.
+        return;
+    }
+
+    for (i, channel_samples) in buffer.iter_samples().enumerate() {
+        let num_channels = channel_samples.len().max(1);
+        let mono: f32 = channel_samples.into_iter().map(|s| *s).sum::<f32>() / num_channels as f32;
+
+        state.ring[state.write_pos] = mono;
+        state.write_pos = (state.write_pos + 1) % WINDOW_SIZE;
+        state.filled = (state.filled + 1).min(WINDOW_SIZE);
+        state.since_last_analysis += 1;
+
+        if state.filled < WINDOW_SIZE || state.since_last_analysis < HOP_SIZE {
+            continue;
+        }
+        state.since_last_analysis = 0;
+
+        for n in 0..WINDOW_SIZE {
+            state.window_scratch[n] = state.ring[(state.write_pos + n) % WINDOW_SIZE];
+        }
+        let window_rms = rms(&state.window_scratch);
+
+        if window_rms < config.gate_threshold {
+            if let Some(note) = state.held_note.take() {
+                context.send_event(NoteEvent::NoteOff {
+                    timing: i as u32,
+                    voice_id: None,
+                    channel: CHANNEL,
+                    note,
+                    velocity: 0.0,
+                });
+            }
+            continue;
+        }
+
+        let Some(freq_hz) = detect_frequency(state, sample_rate) else {
+            continue;
+        };
+        let note = frequency_to_midi_note(freq_hz);
+
+        if state.held_note != Some(note) {
+            if let Some(old_note) = state.held_note {
+                context.send_event(NoteEvent::NoteOff {
+                    timing: i as u32,
+                    voice_id: None,
+                    channel: CHANNEL,
+                    note: old_note,
+                    velocity: 0.0,
+                });
+            }
+            context.send_event(NoteEvent::NoteOn {
+                timing: i as u32,
+                voice_id: None,
+                channel: CHANNEL,
+                note,
+                velocity: rms_to_velocity(window_rms),
+            });
+            state.held_note = Some(note);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequency_to_midi_note_matches_standard_tuning() {
+        assert_eq!(frequency_to_midi_note(440.0), 69); // A4
+        assert_eq!(frequency_to_midi_note(220.0), 57); // A3, one octave down
+        assert_eq!(frequency_to_midi_note(261.63), 60); // middle C
+    }
+
+    #[test]
+    fn rms_of_silence_and_constant_signal() {
+        assert_eq!(rms(&[]), 0.0);
+        assert_eq!(rms(&[0.0, 0.0, 0.0]), 0.0);
+        assert!((rms(&[1.0, -1.0, 1.0, -1.0]) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rms_to_velocity_is_clamped() {
+        assert_eq!(rms_to_velocity(0.0), 0.05);
+        assert_eq!(rms_to_velocity(10.0), 1.0);
+        assert!((rms_to_velocity(0.1) - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn find_pitch_lag_picks_first_dip_below_threshold() {
+        let diff = [1.0, 0.5, 0.3, 0.05, 0.02, 0.3, 0.9];
+        // Walks forward from tau_min=1 to the enclosing local minimum (index 4).
+        assert_eq!(find_pitch_lag(&diff, 1, 6), Some(4));
+    }
+
+    #[test]
+    fn find_pitch_lag_none_when_nothing_crosses_threshold() {
+        let diff = [1.0, 0.9, 0.8, 0.7];
+        assert_eq!(find_pitch_lag(&diff, 1, 3), None);
+    }
+
+    #[test]
+    fn parabolic_interpolate_refines_toward_the_true_minimum() {
+        // A symmetric dip centered exactly at tau=5 should interpolate to 5.0.
+        let diff = [0.0, 0.0, 0.0, 0.0, 0.4, 0.1, 0.4, 0.0];
+        assert!((parabolic_interpolate(&diff, 5) - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn detect_frequency_recovers_a_known_sine_tone() {
+        let sample_rate = 48_000.0f32;
+        let freq_hz = 220.0f32;
+        let mut state = PitchTrackState::new();
+        state.prepare(sample_rate);
+
+        for n in 0..WINDOW_SIZE {
+            let t = n as f32 / sample_rate;
+            state.window_scratch[n] = (2.0 * std::f32::consts::PI * freq_hz * t).sin();
+        }
+
+        let detected = detect_frequency(&mut state, sample_rate).expect("a clean sine tone must be detected");
+        assert!(
+            (detected - freq_hz as f64).abs() < 2.0,
+            "expected ~{freq_hz} Hz, got {detected} Hz"
+        );
+    }
+}
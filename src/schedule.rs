@@ -0,0 +1,159 @@
+//! Sample-accurate sub-buffer note scheduling.
+//!
+//! The audio thread used to check note triggers once per buffer, which puts
+//! every note-on/note-off on the first sample of the block (audible as
+//! timing jitter at large buffer sizes). This module computes the exact
+//! sample offset of each event within the current block instead.
+
+/// One contiguous span of pattern-local beats covered by part of the
+/// current block. `sample_base` is the offset, in beats from the block's
+/// start, at which this span's `local_start` lands - added to an event's
+/// distance into the span to get its true offset from the block start.
+pub struct LoopSegment {
+    pub local_start: f64,
+    pub local_end: f64,
+    pub sample_base: f64,
+}
+
+/// Beats-per-sample at the current tempo/sample-rate, used to turn a
+/// beat offset from the block start into a sample offset.
+pub fn beats_per_sample(tempo: f64, sample_rate: f32) -> f64 {
+    tempo / 60.0 / sample_rate as f64
+}
+
+/// Length of one bar, in quarter-note beats (the unit `pos_beats` and all
+/// the beat math in this plugin use), for a `numerator/denominator` time
+/// signature. A plain `numerator` only happens to be correct in X/4 time;
+/// 6/8 needs `6 * 4/8 = 3` quarter-note beats per bar, not 6.
+pub fn beats_per_bar(numerator: i32, denominator: i32) -> f64 {
+    let numerator = numerator.max(1) as f64;
+    let denominator = denominator.max(1) as f64;
+    numerator * 4.0 / denominator
+}
+
+/// Convert a beat offset from the block's start into a clamped sample
+/// offset within the block (mirrors nih-plug's input/output clamping).
+pub fn sample_offset(beats_from_block_start: f64, beats_per_sample: f64, buffer_len: usize) -> i32 {
+    if beats_per_sample <= 0.0 || buffer_len == 0 {
+        return 0;
+    }
+    let raw = (beats_from_block_start / beats_per_sample).round() as i64;
+    raw.clamp(0, buffer_len as i64 - 1) as i32
+}
+
+/// Split the block's pattern-local beat span `[program_beat_start,
+/// program_beat_start + block_beats)` into one segment, or two if the loop
+/// wraps mid-block (the tail of the current loop, then the head of the next).
+pub fn loop_segments(program_beat_start: f64, block_beats: f64, program_length: f64) -> (LoopSegment, Option<LoopSegment>) {
+    let end_local = program_beat_start + block_beats;
+
+    if program_length <= 0.0 || end_local <= program_length {
+        (
+            LoopSegment { local_start: program_beat_start, local_end: end_local, sample_base: 0.0 },
+            None,
+        )
+    } else {
+        let seg1_beats = program_length - program_beat_start;
+        (
+            LoopSegment { local_start: program_beat_start, local_end: program_length, sample_base: 0.0 },
+            Some(LoopSegment { local_start: 0.0, local_end: end_local - program_length, sample_base: seg1_beats }),
+        )
+    }
+}
+
+/// Test whether `beat` falls within `[seg.local_start, seg.local_end)` and,
+/// if so, return its offset in beats from the block's start.
+pub fn offset_in_segment(beat: f64, seg: &LoopSegment) -> Option<f64> {
+    if beat >= seg.local_start && beat < seg.local_end {
+        Some(seg.sample_base + (beat - seg.local_start))
+    } else {
+        None
+    }
+}
+
+/// Locate a (possibly loop-wrapped) note-off beat within the block's
+/// segments. `end_beat_local` is the raw `start_beat + length_beats`
+/// (unwrapped, so it may exceed `program_length` for notes that sustain
+/// across the loop point); it is normalized against `program_length` to
+/// decide whether it belongs in the current cycle (`seg1`) or the next
+/// one (`seg2`, only present when this block wraps).
+pub fn offset_for_note_off(
+    end_beat_local: f64,
+    program_length: f64,
+    seg1: &LoopSegment,
+    seg2: Option<&LoopSegment>,
+) -> Option<f64> {
+    if program_length <= 0.0 {
+        return offset_in_segment(end_beat_local, seg1);
+    }
+    if end_beat_local < program_length {
+        offset_in_segment(end_beat_local, seg1)
+    } else {
+        let seg2 = seg2?;
+        offset_in_segment(end_beat_local - program_length, seg2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beats_per_bar_common_meters() {
+        assert_eq!(beats_per_bar(4, 4), 4.0);
+        assert_eq!(beats_per_bar(3, 4), 3.0);
+        assert_eq!(beats_per_bar(6, 8), 3.0);
+        assert_eq!(beats_per_bar(7, 8), 3.5);
+        // Zero/negative inputs clamp to 1 instead of dividing by zero.
+        assert_eq!(beats_per_bar(0, 0), 4.0);
+    }
+
+    #[test]
+    fn loop_segments_non_wrapping_block() {
+        let (seg1, seg2) = loop_segments(2.0, 1.0, 16.0);
+        assert_eq!(seg1.local_start, 2.0);
+        assert_eq!(seg1.local_end, 3.0);
+        assert_eq!(seg1.sample_base, 0.0);
+        assert!(seg2.is_none());
+    }
+
+    #[test]
+    fn loop_segments_wraps_mid_block() {
+        let (seg1, seg2) = loop_segments(15.5, 1.0, 16.0);
+        assert_eq!(seg1.local_start, 15.5);
+        assert_eq!(seg1.local_end, 16.0);
+        assert_eq!(seg1.sample_base, 0.0);
+        let seg2 = seg2.expect("block crossing the loop point must produce a second segment");
+        assert_eq!(seg2.local_start, 0.0);
+        assert_eq!(seg2.local_end, 0.5);
+        assert_eq!(seg2.sample_base, 0.5);
+    }
+
+    #[test]
+    fn loop_segments_zero_length_program_never_wraps() {
+        let (seg1, seg2) = loop_segments(100.0, 2.0, 0.0);
+        assert_eq!(seg1.local_start, 100.0);
+        assert_eq!(seg1.local_end, 102.0);
+        assert!(seg2.is_none());
+    }
+
+    #[test]
+    fn offset_in_segment_bounds() {
+        let seg = LoopSegment { local_start: 1.0, local_end: 2.0, sample_base: 0.5 };
+        assert_eq!(offset_in_segment(1.0, &seg), Some(0.5));
+        assert_eq!(offset_in_segment(1.5, &seg), Some(1.0));
+        assert_eq!(offset_in_segment(2.0, &seg), None);
+        assert_eq!(offset_in_segment(0.99, &seg), None);
+    }
+
+    #[test]
+    fn offset_for_note_off_wraps_into_second_segment() {
+        let (seg1, seg2) = loop_segments(15.5, 1.0, 16.0);
+        // A note-off landing in the wrapped tail (pattern-local beat 0.25).
+        let offset = offset_for_note_off(16.25, 16.0, &seg1, seg2.as_ref());
+        assert_eq!(offset, Some(0.75));
+        // A note-off that lands before the loop point stays in seg1.
+        let offset = offset_for_note_off(15.75, 16.0, &seg1, seg2.as_ref());
+        assert_eq!(offset, Some(0.25));
+    }
+}
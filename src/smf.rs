@@ -0,0 +1,275 @@
+//! Standard MIDI File (.mid) import/export for `StagedProgram`.
+//!
+//! This is a thin codec layer: it only knows how to turn the first track of a
+//! Format 0/1 SMF into the fixed `notes` array and back again. It does not
+//! touch `STAGING_DIR` itself beyond a small directory-scan helper; callers
+//! (the Program tab) decide when to read/write actual files.
+
+use crate::{schedule, ProgramNote, StagedProgram, MAX_NOTES};
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind};
+use std::path::{Path, PathBuf};
+
+/// Parse SMF bytes and replace `program`'s notes with the first track's
+/// `NoteOn`/`NoteOff` pairs. Returns `false` (leaving `program` untouched) if
+/// the file can't be parsed or has no usable track.
+pub fn load_smf_bytes(program: &mut StagedProgram, bytes: &[u8]) -> bool {
+    let smf = match Smf::parse(bytes) {
+        Ok(smf) => smf,
+        Err(e) => {
+            nih_plug::nih_log!("Failed to parse SMF: {}", e);
+            return false;
+        }
+    };
+
+    let ppq = match smf.header.timing {
+        Timing::Metrical(ppq) => ppq.as_int() as f64,
+        Timing::Timecode(fps, subframe) => {
+            // Ticks-per-second file; convert to an equivalent ppq at 120 BPM
+            // so downstream beat math stays in the same units.
+            (fps.as_f32() as f64 * subframe as f64) / 2.0
+        }
+    };
+
+    let Some(track) = smf.tracks.first() else {
+        nih_plug::nih_log!("SMF has no tracks");
+        return false;
+    };
+
+    // Pending (start beat, velocity) per (channel, pitch), so we can match
+    // each NoteOn to the next NoteOff of the same pitch/channel and still
+    // know what velocity it was struck at once the note closes.
+    let mut pending: [[Option<(f64, u8)>; 128]; 16] = [[None; 128]; 16];
+    let mut notes: Vec<ProgramNote> = Vec::with_capacity(MAX_NOTES);
+    let mut ticks: u64 = 0;
+    let mut last_end_beat = 0.0f64;
+    // File's own meter, if it has a time signature meta event; falls back
+    // to 4/4 like a program that never specified one.
+    let mut time_sig_numerator = 4u8;
+    let mut time_sig_denominator = 4u8;
+
+    for TrackEvent { delta, kind } in track.iter() {
+        ticks += delta.as_int() as u64;
+        let beat = ticks as f64 / ppq;
+
+        if let TrackEventKind::Meta(MetaMessage::TimeSignature(num, den_pow2, _, _)) = kind {
+            time_sig_numerator = num;
+            time_sig_denominator = 1u8 << den_pow2;
+        }
+
+        if let TrackEventKind::Midi { channel, message } = kind {
+            let ch = channel.as_int() as usize;
+            match message {
+                MidiMessage::NoteOn { key, vel } if vel.as_int() > 0 => {
+                    pending[ch][key.as_int() as usize] = Some((beat, vel.as_int()));
+                }
+                MidiMessage::NoteOn { key, vel: _ } | MidiMessage::NoteOff { key, vel: _ } => {
+                    let pitch = key.as_int() as usize;
+                    if let Some((start_beat, start_vel)) = pending[ch][pitch].take() {
+                        if notes.len() < MAX_NOTES {
+                            let length_beats = (beat - start_beat).max(1.0 / 64.0);
+                            let velocity = start_vel as f32 / 127.0;
+                            notes.push(ProgramNote {
+                                pitch: pitch as u8,
+                                velocity,
+                                start_beat,
+                                length_beats,
+                                active: true,
+                                channel: ch as u8,
+                                expression: None,
+                            });
+                            last_end_beat = last_end_beat.max(start_beat + length_beats);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    program.note_count = notes.len();
+    for (i, note) in notes.into_iter().enumerate() {
+        program.notes[i] = note;
+    }
+    for i in program.note_count..MAX_NOTES {
+        program.notes[i].active = false;
+    }
+
+    program.time_sig_numerator = time_sig_numerator;
+    program.time_sig_denominator = time_sig_denominator;
+    let beats_per_bar = schedule::beats_per_bar(time_sig_numerator as i32, time_sig_denominator as i32);
+
+    // Round the last note end up to the next power-of-two bar count.
+    let bars_needed = (last_end_beat / beats_per_bar).ceil().max(1.0) as u32;
+    let length_bars = bars_needed.next_power_of_two() as f64;
+    program.length_bars = length_bars;
+    program.length_beats = length_bars * beats_per_bar;
+    program.version += 1;
+    program.loaded = true;
+
+    nih_plug::nih_log!(
+        "Imported SMF: {} notes, {} bars",
+        program.note_count,
+        program.length_bars
+    );
+    true
+}
+
+/// Serialize `program` back to a single format-0 SMF track, embedding a
+/// tempo meta-event derived from `tempo_bpm` (defaults to 120 if unknown).
+pub fn export_smf_bytes(program: &StagedProgram, tempo_bpm: Option<f64>) -> Vec<u8> {
+    const PPQ: u16 = 480;
+
+    let tempo_bpm = tempo_bpm.unwrap_or(120.0).max(1.0);
+    let micros_per_beat = (60_000_000.0 / tempo_bpm).round() as u32;
+
+    // (tick, is_note_on, pitch, velocity, channel) flattened event list, sorted by tick.
+    let mut events: Vec<(u64, bool, u8, u8, u8)> = Vec::with_capacity(program.note_count * 2);
+    for i in 0..program.note_count {
+        let note = &program.notes[i];
+        if !note.active {
+            continue;
+        }
+        let start_tick = (note.start_beat * PPQ as f64).round() as u64;
+        let end_tick = ((note.start_beat + note.length_beats) * PPQ as f64).round() as u64;
+        let velocity = (note.velocity * 127.0).round().clamp(1.0, 127.0) as u8;
+        events.push((start_tick, true, note.pitch, velocity, note.channel));
+        events.push((end_tick.max(start_tick + 1), false, note.pitch, 0, note.channel));
+    }
+    events.sort_by_key(|e| e.0);
+
+    let mut track: Vec<TrackEvent<'static>> = Vec::with_capacity(events.len() + 3);
+    track.push(TrackEvent {
+        delta: 0.into(),
+        kind: TrackEventKind::Meta(MetaMessage::Tempo(micros_per_beat.into())),
+    });
+    track.push(TrackEvent {
+        delta: 0.into(),
+        kind: TrackEventKind::Meta(MetaMessage::TimeSignature(
+            program.time_sig_numerator,
+            program.time_sig_denominator.max(1).trailing_zeros() as u8,
+            24,
+            8,
+        )),
+    });
+
+    let mut last_tick = 0u64;
+    for (tick, is_on, pitch, velocity, channel) in events {
+        let delta = (tick - last_tick) as u32;
+        last_tick = tick;
+        let message = if is_on {
+            MidiMessage::NoteOn {
+                key: pitch.into(),
+                vel: velocity.into(),
+            }
+        } else {
+            MidiMessage::NoteOff {
+                key: pitch.into(),
+                vel: 0.into(),
+            }
+        };
+        track.push(TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Midi {
+                channel: channel.into(),
+                message,
+            },
+        });
+    }
+
+    track.push(TrackEvent {
+        delta: 0.into(),
+        kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+    });
+
+    let smf = Smf {
+        header: midly::Header {
+            format: midly::Format::SingleTrack,
+            timing: Timing::Metrical(PPQ.into()),
+        },
+        tracks: vec![track],
+    };
+
+    let mut buf = Vec::new();
+    if let Err(e) = smf.write(&mut buf) {
+        nih_plug::nih_log!("Failed to write SMF: {}", e);
+        return Vec::new();
+    }
+    buf
+}
+
+/// Scan `dir` for `.mid`/`.midi` files a user could import from the Program tab.
+pub fn find_mid_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.eq_ignore_ascii_case("mid") || ext.eq_ignore_ascii_case("midi"))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_then_import_round_trips_note_fields() {
+        let mut program = StagedProgram::default();
+        program.time_sig_numerator = 3;
+        program.time_sig_denominator = 4;
+        program.note_count = 2;
+        program.notes[0] = ProgramNote {
+            pitch: 60,
+            velocity: 1.0,
+            start_beat: 0.0,
+            length_beats: 1.0,
+            active: true,
+            channel: 0,
+            expression: None,
+        };
+        program.notes[1] = ProgramNote {
+            pitch: 67,
+            velocity: 0.25, // 0.25 * 127 rounds to a non-default velocity byte.
+            start_beat: 1.5,
+            length_beats: 0.5,
+            active: true,
+            channel: 0,
+            expression: None,
+        };
+
+        let bytes = export_smf_bytes(&program, Some(120.0));
+
+        let mut imported = StagedProgram::default();
+        assert!(load_smf_bytes(&mut imported, &bytes));
+        assert_eq!(imported.note_count, 2);
+
+        assert_eq!(imported.notes[0].pitch, 60);
+        assert_eq!(imported.notes[0].start_beat, 0.0);
+        assert!((imported.notes[0].velocity - 1.0).abs() < 1e-3);
+
+        assert_eq!(imported.notes[1].pitch, 67);
+        assert_eq!(imported.notes[1].start_beat, 1.5);
+        // Velocity must come from the NoteOn that started the note, not
+        // from the NoteOff that closes it.
+        assert!(
+            (imported.notes[1].velocity - 0.25).abs() < 0.01,
+            "expected velocity ~0.25, got {}",
+            imported.notes[1].velocity
+        );
+
+        assert_eq!(imported.time_sig_numerator, 3);
+        assert_eq!(imported.time_sig_denominator, 4);
+    }
+
+    #[test]
+    fn load_smf_bytes_rejects_garbage() {
+        let mut program = StagedProgram::default();
+        assert!(!load_smf_bytes(&mut program, b"not a midi file"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Persistent WebSocket sync with Gilligan, replacing the old one-shot
+//! registration poll: a background thread holds a single long-lived
+//! connection open for the life of the plugin instance and applies
+//! pushed program updates as they arrive, reconnecting with backoff if
+//! Gilligan restarts or the connection drops.
+
+use crate::triple_buffer::TripleBuffer;
+use crate::{stage_program_launch, PendingLaunch, SharedState, StagedProgram};
+use atomic_refcell::AtomicRefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use tungstenite::Message;
+
+/// Gilligan WebSocket endpoint (same host/port as the REST API, `/ws` path).
+const GILLIGAN_WS_URL: &str = "ws://localhost:61170/api/ws";
+
+/// Reconnect delay after the first failed/dropped attempt, doubling each
+/// further consecutive failure up to `RECONNECT_DELAY_MAX` - so a brief
+/// Gilligan restart reconnects almost immediately, but a dead Gilligan
+/// doesn't get hammered with connection attempts forever.
+const RECONNECT_DELAY_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(30);
+
+/// How often a connected session's blocking `socket.read()` wakes up on its
+/// own (via a socket read timeout) just to check `shutdown` - short enough
+/// that tearing down a plugin instance doesn't leave its sync thread (and
+/// the `Arc`s it holds) running noticeably past `deactivate()`/`Drop`, long
+/// enough not to matter for an otherwise-idle connection.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Owns the background sync thread spawned by [`spawn`]: setting `shutdown`
+/// and joining `thread` is how a [`crate::Skipper`] instance stops leaking
+/// this thread (and the state/buffers it captured) past `deactivate()`.
+pub struct SyncHandle {
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncHandle {
+    /// Signal the background thread to stop at its next opportunity (the
+    /// track-info wait loop, a connected session's read-timeout poll, or the
+    /// reconnect backoff sleep) and block until it has actually exited.
+    pub fn shutdown(&mut self) {
+        self.shutdown.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SyncHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Connection state to Gilligan, mirrored into `SharedState::sync_status`
+/// and rendered in the Info tab - see `build_info_text`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// No connection attempt has finished yet. The sync thread never sets
+    /// this state again: once an attempt ends, the status is `Reconnecting`
+    /// until a session opens.
+    Offline,
+    /// A WebSocket session to Gilligan is currently open.
+    Connected,
+    /// An attempt failed or a session ended (cleanly or not), and the
+    /// backoff sleep or the next attempt is in progress.
+    Reconnecting,
+}
+
+impl Default for ConnectionStatus {
+    fn default() -> Self {
+        ConnectionStatus::Offline
+    }
+}
+
+/// Spawn the background sync thread. Runs for the lifetime of the plugin
+/// instance: waits for track info to be available, then holds a WebSocket
+/// open to Gilligan and applies every pushed `{"program": ...}` message to
+/// `state`, reconnecting indefinitely (with backoff) instead of giving up
+/// after one try.
+pub fn spawn(
+    state: Arc<AtomicRefCell<SharedState>>,
+    program_buf: Arc<TripleBuffer<StagedProgram>>,
+    pending_launch_buf: Arc<TripleBuffer<PendingLaunch>>,
+    pending_armed: Arc<AtomicBool>,
+    instance_id: u32,
+) -> SyncHandle {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let thread_shutdown = shutdown.clone();
+
+    let thread = std::thread::spawn(move || {
+        let shutdown = thread_shutdown;
+        let track_name = loop {
+            if shutdown.load(Ordering::Acquire) {
+                return;
+            }
+            let name = state
+                .try_borrow()
+                .ok()
+                .and_then(|s| s.track_info.as_ref().and_then(|t| t.name.clone()))
+                .filter(|n| !n.is_empty());
+
+            if let Some(name) = name {
+                break name;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        };
+
+        let uuid = format!("skipper-{}", instance_id);
+        nih_plug::nih_log!("Starting persistent Gilligan sync: uuid={}, track={}", uuid, track_name);
+
+        let mut consecutive_failures: u32 = 0;
+        loop {
+            if shutdown.load(Ordering::Acquire) {
+                return;
+            }
+            match run_session(&state, &program_buf, &pending_launch_buf, &pending_armed, &uuid, &track_name, &shutdown) {
+                Ok(()) => {
+                    nih_plug::nih_log!("Gilligan WebSocket session ended cleanly, reconnecting");
+                    consecutive_failures = 0;
+                }
+                Err(e) => {
+                    nih_plug::nih_log!("Gilligan WebSocket session failed: {}, reconnecting", e);
+                    consecutive_failures = consecutive_failures.saturating_add(1);
+                }
+            }
+            if shutdown.load(Ordering::Acquire) {
+                return;
+            }
+            set_status(&state, ConnectionStatus::Reconnecting);
+            std::thread::sleep(reconnect_delay(consecutive_failures));
+        }
+    });
+
+    SyncHandle { shutdown, thread: Some(thread) }
+}
+
+/// Backoff schedule: no failures yet (a clean disconnect) reconnects right
+/// away, then `RECONNECT_DELAY_MIN` doubling per consecutive failure up to
+/// `RECONNECT_DELAY_MAX`.
+fn reconnect_delay(consecutive_failures: u32) -> Duration {
+    if consecutive_failures == 0 {
+        return Duration::ZERO;
+    }
+    let shift = (consecutive_failures - 1).min(8);
+    (RECONNECT_DELAY_MIN * 2u32.pow(shift)).min(RECONNECT_DELAY_MAX)
+}
+
+fn set_status(state: &Arc<AtomicRefCell<SharedState>>, status: ConnectionStatus) {
+    if let Ok(mut s) = state.try_borrow_mut() {
+        s.sync_status = status;
+    }
+}
+
+fn mark_update(state: &Arc<AtomicRefCell<SharedState>>) {
+    if let Ok(mut s) = state.try_borrow_mut() {
+        s.sync_last_update = Some(SystemTime::now());
+    }
+}
+
+/// Connect to Gilligan, send the `register` hello, and apply pushed
+/// program updates until the connection closes, errors, or `shutdown` is
+/// set (checked every [`SHUTDOWN_POLL_INTERVAL`] via a read timeout on the
+/// socket, so a torn-down plugin instance's thread notices promptly instead
+/// of blocking on `read()` until Gilligan happens to send something).
+fn run_session(
+    state: &Arc<AtomicRefCell<SharedState>>,
+    program_buf: &Arc<TripleBuffer<StagedProgram>>,
+    pending_launch_buf: &Arc<TripleBuffer<PendingLaunch>>,
+    pending_armed: &Arc<AtomicBool>,
+    uuid: &str,
+    track_name: &str,
+    shutdown: &Arc<AtomicBool>,
+) -> Result<(), String> {
+    let (mut socket, _response) = tungstenite::connect(GILLIGAN_WS_URL).map_err(|e| e.to_string())?;
+    socket
+        .get_ref()
+        .set_read_timeout(Some(SHUTDOWN_POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+
+    let hello = serde_json::json!({
+        "type": "register",
+        "uuid": uuid,
+        "track": track_name,
+    });
+    socket.send(Message::Text(hello.to_string())).map_err(|e| e.to_string())?;
+
+    set_status(state, ConnectionStatus::Connected);
+    mark_update(state);
+
+    loop {
+        if shutdown.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                apply_message(state, program_buf, pending_launch_buf, pending_armed, &text);
+                mark_update(state);
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(ref e))
+                if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                // Just the read-timeout poll above firing with nothing to
+                // read - not a real connection failure.
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Parse a pushed message and, if it carries a `program` payload, stage it
+/// through [`stage_program_launch`] under the track's current launch
+/// quantization setting - an `Immediate` track swaps right away, same as
+/// before; anything else waits for `process()` to cross the next bar/beat
+/// boundary instead of cutting in on whatever's already playing.
+fn apply_message(
+    state: &Arc<AtomicRefCell<SharedState>>,
+    program_buf: &Arc<TripleBuffer<StagedProgram>>,
+    pending_launch_buf: &Arc<TripleBuffer<PendingLaunch>>,
+    pending_armed: &Arc<AtomicBool>,
+    text: &str,
+) {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(text) else {
+        return;
+    };
+    let Some(program_json) = json.get("program").filter(|p| !p.is_null()) else {
+        return;
+    };
+
+    let mut program = StagedProgram::default();
+    if !program.load_from_json(program_json) {
+        return;
+    }
+
+    let quantization = state
+        .try_borrow()
+        .map(|s| s.launch_quantization)
+        .unwrap_or_default();
+
+    if let Ok(mut s) = state.try_borrow_mut() {
+        s.program = program.clone();
+    }
+    stage_program_launch(program_buf, pending_launch_buf, pending_armed, quantization, program);
+}
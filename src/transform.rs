@@ -0,0 +1,164 @@
+//! Pattern-transform pipeline over a `StagedProgram`.
+//!
+//! Each transform takes a program by reference and returns a transformed
+//! copy; none of these mutate in place, so callers can preview, chain, or
+//! discard a transform before committing it with `program.version += 1`.
+
+use crate::{ProgramNote, StagedProgram, MAX_NOTES};
+
+/// Major scale pitch-class mask (semitones from the root).
+pub const SCALE_MAJOR: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+/// Natural minor scale pitch-class mask.
+pub const SCALE_MINOR: [u8; 7] = [0, 2, 3, 5, 7, 8, 10];
+
+/// Mirror notes in time within `program.length_beats`:
+/// `new_start = length_beats - start_beat - length_beats_of_note`.
+pub fn rev(program: &StagedProgram) -> StagedProgram {
+    let mut out = program.clone();
+    let total = program.length_beats;
+    for i in 0..out.note_count {
+        let note = program.notes[i];
+        if !note.active {
+            continue;
+        }
+        out.notes[i].start_beat = total - note.start_beat - note.length_beats;
+    }
+    out.version = program.version.wrapping_add(1);
+    out
+}
+
+/// Duplicate every note `n` times, each copy shifted by `beat_offset` and
+/// with `velocity *= decay`, wrapping within `length_beats`. Drops overflow
+/// past `MAX_NOTES`.
+pub fn echo(program: &StagedProgram, n: u32, beat_offset: f64, decay: f32) -> StagedProgram {
+    let mut out = program.clone();
+    let total = program.length_beats;
+
+    let mut notes: Vec<ProgramNote> = Vec::with_capacity(MAX_NOTES);
+    for i in 0..program.note_count {
+        let note = program.notes[i];
+        if !note.active {
+            continue;
+        }
+        for copy in 0..n {
+            if notes.len() >= MAX_NOTES {
+                break;
+            }
+            let mut shifted = note;
+            let mut start = note.start_beat + beat_offset * copy as f64;
+            if total > 0.0 {
+                start %= total;
+            }
+            shifted.start_beat = start;
+            shifted.velocity = note.velocity * decay.powi(copy as i32);
+            notes.push(shifted);
+        }
+    }
+
+    out.note_count = notes.len();
+    for (i, note) in notes.into_iter().enumerate() {
+        out.notes[i] = note;
+    }
+    for i in out.note_count..MAX_NOTES {
+        out.notes[i].active = false;
+    }
+    out.version = program.version.wrapping_add(1);
+    out
+}
+
+/// Snap `pitch` to the nearest member of `scale_mask` (pitch classes
+/// relative to `root`), searching outward +/-1, +/-2 semitones from the
+/// original pitch-class before giving up and leaving the pitch unchanged.
+fn quantize_pitch(pitch: u8, root: u8, scale_mask: &[u8]) -> u8 {
+    let pitch_class = (pitch as i32 - root as i32).rem_euclid(12);
+
+    for offset in [0i32, -1, 1, -2, 2] {
+        let candidate_class = (pitch_class + offset).rem_euclid(12);
+        if scale_mask.contains(&(candidate_class as u8)) {
+            let new_pitch = pitch as i32 + offset;
+            return new_pitch.clamp(0, 127) as u8;
+        }
+    }
+    pitch
+}
+
+/// Snap every note's `pitch` to the nearest pitch class in `scale_mask`
+/// (relative to `root`).
+pub fn scale_quantize(program: &StagedProgram, root: u8, scale_mask: &[u8]) -> StagedProgram {
+    let mut out = program.clone();
+    for i in 0..out.note_count {
+        if !program.notes[i].active {
+            continue;
+        }
+        out.notes[i].pitch = quantize_pitch(program.notes[i].pitch, root, scale_mask);
+    }
+    out.version = program.version.wrapping_add(1);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_with(notes: &[(f64, f64)]) -> StagedProgram {
+        let mut program = StagedProgram::default();
+        program.length_beats = 8.0;
+        program.note_count = notes.len();
+        for (i, &(start_beat, length_beats)) in notes.iter().enumerate() {
+            program.notes[i] = ProgramNote {
+                pitch: 60,
+                velocity: 0.8,
+                start_beat,
+                length_beats,
+                active: true,
+                channel: 0,
+                expression: None,
+            };
+        }
+        program
+    }
+
+    #[test]
+    fn rev_mirrors_start_beats_within_length() {
+        let program = program_with(&[(0.0, 1.0), (2.0, 1.0)]);
+        let reversed = rev(&program);
+        assert_eq!(reversed.notes[0].start_beat, 7.0);
+        assert_eq!(reversed.notes[1].start_beat, 5.0);
+        assert_eq!(reversed.version, program.version.wrapping_add(1));
+    }
+
+    #[test]
+    fn echo_duplicates_with_offset_and_decay() {
+        let program = program_with(&[(0.0, 1.0)]);
+        let out = echo(&program, 3, 1.0, 0.5);
+        assert_eq!(out.note_count, 3);
+        assert_eq!(out.notes[0].start_beat, 0.0);
+        assert_eq!(out.notes[1].start_beat, 1.0);
+        assert_eq!(out.notes[2].start_beat, 2.0);
+        assert!((out.notes[0].velocity - 0.8).abs() < 1e-6);
+        assert!((out.notes[1].velocity - 0.4).abs() < 1e-6);
+        assert!((out.notes[2].velocity - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn echo_wraps_start_beat_within_program_length() {
+        let program = program_with(&[(7.0, 0.5)]);
+        let out = echo(&program, 2, 2.0, 1.0);
+        // Second copy's start (7.0 + 2.0 = 9.0) wraps modulo length_beats (8.0).
+        assert_eq!(out.notes[1].start_beat, 1.0);
+    }
+
+    #[test]
+    fn scale_quantize_snaps_to_nearest_scale_tone() {
+        let mut program = program_with(&[(0.0, 1.0)]);
+        program.notes[0].pitch = 61; // C#, not in C major
+        let out = scale_quantize(&program, 60, &SCALE_MAJOR);
+        assert_eq!(out.notes[0].pitch, 60);
+
+        // In-scale notes pass through unchanged.
+        let mut in_scale = program_with(&[(0.0, 1.0)]);
+        in_scale.notes[0].pitch = 64; // E, in C major
+        let out = scale_quantize(&in_scale, 60, &SCALE_MAJOR);
+        assert_eq!(out.notes[0].pitch, 64);
+    }
+}
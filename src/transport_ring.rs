@@ -0,0 +1,126 @@
+//! Single-producer/single-consumer ring buffer for mirroring transport state
+//! from the audio thread to the GUI, replacing a shared `RefCell` write that
+//! could be skipped whenever the GUI held the borrow during its own frame.
+//! The audio thread only ever pushes (never allocates, never blocks) and the
+//! GUI only ever drains - there's no lock on either side, and `head`/`tail`
+//! are each mutated by exactly one of the two, so a written slot is never
+//! concurrently read.
+
+use crate::TransportState;
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const CAPACITY: usize = 64;
+
+pub struct TransportRing {
+    slots: [UnsafeCell<TransportState>; CAPACITY],
+    /// Next slot the producer will write to. Producer-owned.
+    head: AtomicUsize,
+    /// Next slot the consumer will read from. Consumer-owned.
+    tail: AtomicUsize,
+}
+
+// SAFETY: `head` is only ever advanced by the single producer and `tail`
+// only by the single consumer, so each slot is written by at most one
+// thread at a time and read only once `head` has made it visible.
+unsafe impl Sync for TransportRing {}
+
+impl Default for TransportRing {
+    fn default() -> Self {
+        Self {
+            slots: std::array::from_fn(|_| UnsafeCell::new(TransportState::default())),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl TransportRing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Producer side (the audio thread, once per `process()` call). Never
+    /// blocks and never touches `tail` - if the GUI has fallen behind and
+    /// the ring is full, this snapshot is dropped rather than overwriting a
+    /// slot the consumer hasn't read yet. The GUI only ever wants the
+    /// latest position, so a dropped snapshot is harmless; it catches up as
+    /// soon as it next drains.
+    pub fn push(&self, value: TransportState) {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head.wrapping_sub(tail) >= CAPACITY {
+            return;
+        }
+        // SAFETY: only the producer writes to `slots[head]`, and `head` is
+        // within `CAPACITY` of `tail` (checked above), so this slot isn't
+        // one the consumer could currently be reading.
+        unsafe { *self.slots[head % CAPACITY].get() = value };
+        self.head.store(head + 1, Ordering::Release);
+    }
+
+    /// Consumer side (the GUI, once per frame). Drains every snapshot
+    /// pushed since the last call and returns only the most recent one -
+    /// the GUI only ever wants "now", not a full history.
+    pub fn drain_latest(&self) -> Option<TransportState> {
+        let head = self.head.load(Ordering::Acquire);
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        if tail == head {
+            return None;
+        }
+
+        let mut latest = None;
+        while tail != head {
+            // SAFETY: the producer has already published up to `head`, and
+            // only the consumer advances `tail`, so slots in `[tail, head)`
+            // are ours alone to read.
+            latest = Some(unsafe { (*self.slots[tail % CAPACITY].get()).clone() });
+            tail += 1;
+        }
+        self.tail.store(tail, Ordering::Release);
+        latest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(pos_beats: f64) -> TransportState {
+        TransportState {
+            pos_beats: Some(pos_beats),
+            ..TransportState::default()
+        }
+    }
+
+    #[test]
+    fn drain_latest_returns_none_when_empty() {
+        let ring = TransportRing::new();
+        assert!(ring.drain_latest().is_none());
+    }
+
+    #[test]
+    fn drain_latest_collapses_multiple_pushes_to_the_newest() {
+        let ring = TransportRing::new();
+        ring.push(state(1.0));
+        ring.push(state(2.0));
+        ring.push(state(3.0));
+
+        let latest = ring.drain_latest().expect("ring had pushes pending");
+        assert_eq!(latest.pos_beats, Some(3.0));
+        assert!(ring.drain_latest().is_none());
+    }
+
+    #[test]
+    fn push_drops_snapshots_once_the_ring_is_full() {
+        let ring = TransportRing::new();
+        for i in 0..(CAPACITY + 10) {
+            ring.push(state(i as f64));
+        }
+        // Once the ring is full, newer pushes are dropped rather than
+        // overwriting unread slots; draining yields the last snapshot that
+        // fit, not the most recent push.
+        let latest = ring.drain_latest().expect("ring had pushes pending");
+        assert_eq!(latest.pos_beats, Some((CAPACITY - 1) as f64));
+    }
+}
@@ -0,0 +1,195 @@
+//! A wait-free triple buffer for handing a full value between threads
+//! without the consuming side ever blocking or silently missing an update.
+//!
+//! Three slots are split at all times into a "front" slot exclusively owned
+//! by the designated reader, a "back" slot exclusively owned by whichever
+//! writer currently holds [`TripleBuffer::back`]'s lock, and a third slot
+//! floating between them that always holds the most recently published
+//! value. Publishing and picking up a publish are each a single atomic
+//! exchange on `state`, so the reader never takes a lock and the writer only
+//! ever contends with *other writers* - never with the reader.
+//!
+//! This plugin has more than one non-realtime writer (the GUI thread and the
+//! Gilligan sync thread both stage new programs), so the writer side is
+//! serialized with an ordinary [`Mutex`]; that's fine, since neither of
+//! those threads is realtime. [`TripleBuffer::try_write`] gives the audio
+//! thread a non-blocking writer path too (for control-surface/capture edits
+//! made in `process()`) that simply skips the edit on the rare occasion it
+//! loses the race for the lock, rather than ever stalling the audio thread.
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+const INDEX_MASK: u8 = 0b011;
+const NEW_FLAG: u8 = 0b100;
+
+pub struct TripleBuffer<T> {
+    slots: [UnsafeCell<T>; 3],
+    /// Packs the index (0..=2) of the most recently published slot together
+    /// with a flag for whether the reader has picked it up yet.
+    state: AtomicU8,
+    /// Index of the slot writers may freely mutate, serialized across
+    /// however many non-realtime writer threads there are.
+    back: Mutex<usize>,
+}
+
+// SAFETY: the three slots are only ever aliased by at most one reader and
+// one writer at a time (enforced by the index bookkeeping below), so `T`
+// crossing threads via shared references into `UnsafeCell` is sound as long
+// as `T` itself is `Send`.
+unsafe impl<T: Send> Sync for TripleBuffer<T> {}
+
+impl<T: Clone> TripleBuffer<T> {
+    /// Build a triple buffer with every slot initialized to a clone of
+    /// `initial`. Readers should start with a reader index of `2` (slots `0`
+    /// and `1` are already claimed as the initial published/back slots).
+    pub fn new(initial: T) -> Self {
+        Self {
+            slots: [
+                UnsafeCell::new(initial.clone()),
+                UnsafeCell::new(initial.clone()),
+                UnsafeCell::new(initial),
+            ],
+            state: AtomicU8::new(NEW_FLAG), // slot 0, flagged so the first read() picks it up
+            back: Mutex::new(1),
+        }
+    }
+
+    /// The reader index a fresh [`TripleBuffer`] expects its sole reader to
+    /// start from.
+    pub const INITIAL_READER_INDEX: usize = 2;
+
+    fn publish(&self, back_idx: &mut usize, f: impl FnOnce(&mut T)) {
+        // SAFETY: `back_idx` is writer-exclusive - no reader or other writer
+        // can be touching this slot while we hold the `back` lock.
+        f(unsafe { &mut *self.slots[*back_idx].get() });
+        let published = (*back_idx as u8) | NEW_FLAG;
+        let old = self.state.swap(published, Ordering::AcqRel);
+        *back_idx = (old & INDEX_MASK) as usize;
+    }
+
+    /// Non-realtime writer path (GUI thread, background sync thread):
+    /// blocks briefly if another writer is mid-publish, which is fine since
+    /// neither caller runs on the audio thread.
+    pub fn write(&self, f: impl FnOnce(&mut T)) {
+        let mut back_idx = self.back.lock().unwrap();
+        self.publish(&mut *back_idx, f);
+    }
+
+    /// Realtime-safe writer path (audio thread: control-surface pad toggles,
+    /// live-capture note-offs). Never blocks - on the rare occasion another
+    /// writer is mid-publish (a handful of instructions), the edit is
+    /// dropped for this call instead of stalling the audio thread. Returns
+    /// whether the edit was applied.
+    pub fn try_write(&self, f: impl FnOnce(&mut T)) -> bool {
+        let Ok(mut back_idx) = self.back.try_lock() else {
+            return false;
+        };
+        self.publish(&mut *back_idx, f);
+        true
+    }
+
+    /// Realtime-safe reader path (the audio thread's sole designated
+    /// reader). `reader_idx` is the caller's own bookkeeping, starting at
+    /// [`Self::INITIAL_READER_INDEX`] - if a new value was published since
+    /// the last call, claims it and hands the old front slot back to
+    /// writers; otherwise just re-reads the unchanged front. Never blocks,
+    /// never allocates.
+    pub fn read<'a>(&'a self, reader_idx: &mut usize) -> &'a T {
+        let current = self.state.load(Ordering::Acquire);
+        if current & NEW_FLAG != 0 {
+            // Atomically claim the new value, handing back our old front
+            // slot (safe to reuse now - we're about to stop reading it) as
+            // the new floating slot. If a writer published again between
+            // our load and this exchange, state has already moved on; leave
+            // reader_idx alone and pick up the newer value next call rather
+            // than spinning on the audio thread.
+            if self
+                .state
+                .compare_exchange(current, *reader_idx as u8, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                *reader_idx = (current & INDEX_MASK) as usize;
+            }
+        }
+        // SAFETY: `reader_idx` always names the slot this is the sole
+        // reader of - no writer can claim it without first observing (via
+        // the exchange above) that we've moved on.
+        unsafe { &*self.slots[*reader_idx].get() }
+    }
+
+    /// Lock-free peek at the most recently published value, for threads
+    /// that just want to display it without becoming the designated reader
+    /// (e.g. the GUI, alongside the audio thread's [`Self::read`]). Race-free
+    /// because a writer only ever mutates the slot it currently holds via
+    /// `back`, which by construction is never the slot `state` points at.
+    pub fn snapshot(&self) -> T {
+        let idx = (self.state.load(Ordering::Acquire) & INDEX_MASK) as usize;
+        // SAFETY: see above - the published slot is never concurrently
+        // written to.
+        unsafe { (*self.slots[idx].get()).clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_picks_up_published_values_in_order() {
+        let buf = TripleBuffer::new(0i32);
+        let mut reader_idx = TripleBuffer::<i32>::INITIAL_READER_INDEX;
+
+        assert_eq!(*buf.read(&mut reader_idx), 0);
+
+        buf.write(|v| *v = 1);
+        assert_eq!(*buf.read(&mut reader_idx), 1);
+
+        buf.write(|v| *v = 2);
+        buf.write(|v| *v = 3);
+        assert_eq!(*buf.read(&mut reader_idx), 3);
+
+        // No new publish since the last read: re-reads the same value.
+        assert_eq!(*buf.read(&mut reader_idx), 3);
+    }
+
+    #[test]
+    fn try_write_applies_edit_when_uncontended() {
+        let buf = TripleBuffer::new(10i32);
+        assert!(buf.try_write(|v| *v = 20));
+        let mut reader_idx = TripleBuffer::<i32>::INITIAL_READER_INDEX;
+        assert_eq!(*buf.read(&mut reader_idx), 20);
+    }
+
+    #[test]
+    fn snapshot_sees_latest_publish_without_becoming_the_reader() {
+        let buf = TripleBuffer::new(vec![1, 2, 3]);
+        assert_eq!(buf.snapshot(), vec![1, 2, 3]);
+        buf.write(|v| v.push(4));
+        assert_eq!(buf.snapshot(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn concurrent_writer_and_reader_never_observe_a_torn_value() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let buf = Arc::new(TripleBuffer::new(0u64));
+        let writer_buf = Arc::clone(&buf);
+        let writer = thread::spawn(move || {
+            for i in 1..=10_000u64 {
+                writer_buf.write(|v| *v = i);
+            }
+        });
+
+        let mut reader_idx = TripleBuffer::<u64>::INITIAL_READER_INDEX;
+        let mut last_seen = 0u64;
+        for _ in 0..10_000 {
+            let seen = *buf.read(&mut reader_idx);
+            assert!(seen >= last_seen, "reader must never see values go backwards");
+            last_seen = seen;
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,448 @@
+//! Optional user-extensible DSP chain: `*.wasm` modules dropped into a
+//! per-plugin config directory are sandboxed (via `wasmtime`) and run as
+//! extra processing stages after the built-in synth/pitch-tracking paths.
+//!
+//! Modules are discovered and instantiated on a background reload-watcher
+//! thread spawned at `initialize()` (see [`spawn_reload_watcher`]), which
+//! rescans whenever [`ControlMessage::Reload`] asks for it and otherwise
+//! every five seconds - never on the audio thread, since instantiating a
+//! guest module allocates. The freshly built chain is handed to the audio thread through
+//! the same wait-free [`crate::triple_buffer::TripleBuffer`] this crate
+//! already uses for `program_buf`/`pending_launch_buf`: the reader swaps to
+//! the new chain at the next block boundary, and the old one is dropped
+//! once the audio thread has moved off it.
+//!
+//! # Guest ABI
+//!
+//! A module exports:
+//! - `memory`: the linear memory the host reads/writes samples through.
+//! - `skipper_dsp_alloc(len: i32) -> i32`: reserve `len` bytes in guest
+//!   memory for the host to write into, returning the offset.
+//! - `skipper_dsp_init() -> i32`: returns the offset of a length-prefixed
+//!   descriptor buffer (see [`parse_descriptor`]) declaring the module's
+//!   name, version, and parameters.
+//! - `skipper_dsp_process(ptr: i32, frame_count: i32, channel_count: i32)`:
+//!   processes `frame_count * channel_count` interleaved `f32` samples
+//!   in place starting at `ptr`, so the host never holds a guest pointer
+//!   across calls - it writes the block in, calls this, then reads the
+//!   same range back out.
+//! - `skipper_dsp_set_param(index: i32, value: f32)` and
+//!   `skipper_dsp_reset()`, both called from [`ControlMessage`] handling.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use wasmtime::{Config, Engine, Instance, Memory, Module, Store, TypedFunc};
+
+/// Fuel granted for `skipper_dsp_init`/`skipper_dsp_set_param`/
+/// `skipper_dsp_reset` calls - generous, since none of these run on the
+/// audio thread or process per-sample work, but still finite so a guest
+/// stuck in a loop on any entry point traps instead of hanging its caller.
+const FUEL_CONTROL_CALL: u64 = 10_000_000;
+
+/// Fuel granted per `skipper_dsp_process` call, scaled to the block size so
+/// legitimate per-sample work isn't starved on large blocks while a guest
+/// stuck in an infinite/slow loop still traps well within one audio-thread
+/// deadline instead of running forever. `FUEL_BASE` covers fixed per-call
+/// overhead (the `alloc_fn` call when the scratch buffer grows, a module's
+/// own setup work); `FUEL_PER_SAMPLE` covers per-sample processing.
+const FUEL_BASE: u64 = 1_000_000;
+const FUEL_PER_SAMPLE: u64 = 10_000;
+
+/// User-facing config, edited from the Live tab.
+#[derive(Clone)]
+pub struct WasmDspConfig {
+    pub enabled: bool,
+    /// Directory scanned for `*.wasm` modules.
+    pub module_dir: PathBuf,
+}
+
+impl Default for WasmDspConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            module_dir: PathBuf::from("skipper_dsp_modules"),
+        }
+    }
+}
+
+/// One parameter a guest module declares in its `skipper_dsp_init` descriptor.
+#[derive(Clone)]
+pub struct ParamDescriptor {
+    pub name: String,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// A guest module's declared identity and parameters, returned by
+/// `skipper_dsp_init` and shown in the GUI so a user can see what's loaded.
+#[derive(Clone)]
+pub struct ModuleDescriptor {
+    pub name: String,
+    pub version: String,
+    pub params: Vec<ParamDescriptor>,
+}
+
+/// One instantiated, sandboxed guest module plus the cached handles needed
+/// to drive it every block. `store` is wrapped in a `RefCell` rather than
+/// accessed through `&mut` directly, since the chain this lives in is
+/// handed to the audio thread as a shared `&Arc<Vec<ModuleInstance>>` via
+/// `TripleBuffer::read` - the audio thread is still the only one ever
+/// touching it, just not through a uniquely-owned reference.
+pub struct ModuleInstance {
+    pub descriptor: ModuleDescriptor,
+    store: RefCell<Store<()>>,
+    memory: Memory,
+    alloc_fn: TypedFunc<i32, i32>,
+    process_fn: TypedFunc<(i32, i32, i32), ()>,
+    set_param_fn: TypedFunc<(i32, f32), ()>,
+    reset_fn: TypedFunc<(), ()>,
+    /// Guest memory offset of the scratch buffer reserved for the largest
+    /// block size processed so far, reused across calls rather than
+    /// re-allocating in the guest every block. `Cell`s because `process`
+    /// only ever gets `&self` (see the struct's own doc comment) but still
+    /// needs to remember the allocation across calls.
+    scratch_ptr: std::cell::Cell<i32>,
+    scratch_len: std::cell::Cell<i32>,
+}
+
+impl ModuleInstance {
+    fn load(engine: &Engine, path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let module = Module::new(engine, &bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut store = Store::new(engine, ());
+        let linker = wasmtime::Linker::new(engine);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| format!("{}: missing exported memory", path.display()))?;
+        let alloc_fn: TypedFunc<i32, i32> = get_typed_func(&instance, &mut store, "skipper_dsp_alloc", path)?;
+        let init_fn: TypedFunc<(), i32> = get_typed_func(&instance, &mut store, "skipper_dsp_init", path)?;
+        let process_fn: TypedFunc<(i32, i32, i32), ()> =
+            get_typed_func(&instance, &mut store, "skipper_dsp_process", path)?;
+        let set_param_fn: TypedFunc<(i32, f32), ()> =
+            get_typed_func(&instance, &mut store, "skipper_dsp_set_param", path)?;
+        let reset_fn: TypedFunc<(), ()> = get_typed_func(&instance, &mut store, "skipper_dsp_reset", path)?;
+
+        let _ = store.set_fuel(FUEL_CONTROL_CALL);
+        let descriptor_ptr = init_fn
+            .call(&mut store, ())
+            .map_err(|e| format!("{}: skipper_dsp_init trapped: {e}", path.display()))?;
+        let descriptor = parse_descriptor(&memory, &mut store, descriptor_ptr)
+            .map_err(|e| format!("{}: bad descriptor: {e}", path.display()))?;
+
+        Ok(Self {
+            descriptor,
+            store: RefCell::new(store),
+            memory,
+            alloc_fn,
+            process_fn,
+            set_param_fn,
+            reset_fn,
+            scratch_ptr: std::cell::Cell::new(0),
+            scratch_len: std::cell::Cell::new(0),
+        })
+    }
+
+    /// Process one block in place: `samples` is interleaved
+    /// `frame_count * channel_count` `f32`s, written into the guest,
+    /// processed, and read back over the same slice.
+    fn process(&self, samples: &mut [f32], frame_count: usize, channel_count: usize) {
+        let mut store = self.store.borrow_mut();
+        let byte_len = (samples.len() * std::mem::size_of::<f32>()) as i32;
+
+        // Budget this whole block - including the scratch-buffer `alloc_fn`
+        // call just below, when it runs - so a module that traps partway
+        // through is cut off instead of hanging the audio thread, and its
+        // output is simply skipped for this block (see the `is_ok()` check
+        // below, which already treats a trap the same as any other guest
+        // failure).
+        let _ = store.set_fuel(FUEL_BASE + samples.len() as u64 * FUEL_PER_SAMPLE);
+
+        if byte_len > self.scratch_len.get() {
+            let Ok(ptr) = self.alloc_fn.call(&mut store, byte_len) else { return };
+            self.scratch_ptr.set(ptr);
+            self.scratch_len.set(byte_len);
+        }
+
+        let ptr = self.scratch_ptr.get();
+        write_samples(&self.memory, &mut store, ptr, samples);
+        if self
+            .process_fn
+            .call(&mut store, (ptr, frame_count as i32, channel_count as i32))
+            .is_ok()
+        {
+            read_samples(&self.memory, &mut store, ptr, samples);
+        }
+    }
+
+    fn set_param(&self, index: usize, value: f32) {
+        let mut store = self.store.borrow_mut();
+        let _ = store.set_fuel(FUEL_CONTROL_CALL);
+        let _ = self.set_param_fn.call(&mut store, (index as i32, value));
+    }
+
+    fn reset(&self) {
+        let mut store = self.store.borrow_mut();
+        let _ = store.set_fuel(FUEL_CONTROL_CALL);
+        let _ = self.reset_fn.call(&mut store, ());
+    }
+}
+
+fn get_typed_func<Params, Results>(
+    instance: &Instance,
+    store: &mut Store<()>,
+    name: &str,
+    path: &Path,
+) -> Result<TypedFunc<Params, Results>, String>
+where
+    Params: wasmtime::WasmParams,
+    Results: wasmtime::WasmResults,
+{
+    instance
+        .get_typed_func(store, name)
+        .map_err(|e| format!("{}: missing export `{name}`: {e}", path.display()))
+}
+
+fn write_samples(memory: &Memory, store: &mut Store<()>, ptr: i32, samples: &[f32]) {
+    let bytes: &[u8] =
+        unsafe { std::slice::from_raw_parts(samples.as_ptr() as *const u8, samples.len() * std::mem::size_of::<f32>()) };
+    let _ = memory.write(store, ptr as usize, bytes);
+}
+
+fn read_samples(memory: &Memory, store: &mut Store<()>, ptr: i32, samples: &mut [f32]) {
+    let bytes: &mut [u8] = unsafe {
+        std::slice::from_raw_parts_mut(samples.as_mut_ptr() as *mut u8, samples.len() * std::mem::size_of::<f32>())
+    };
+    let _ = memory.read(store, ptr as usize, bytes);
+}
+
+/// Parse the length-prefixed descriptor buffer a module returns from
+/// `skipper_dsp_init`: `[u32 name_len][name][u32 version_len][version]
+/// [u32 param_count]([f32 default][f32 min][f32 max][u32 name_len][name])*`.
+fn parse_descriptor(memory: &Memory, store: &mut Store<()>, ptr: i32) -> Result<ModuleDescriptor, String> {
+    let data = memory.data(store);
+    let mut pos = ptr as usize;
+
+    let name = read_length_prefixed_string(data, &mut pos)?;
+    let version = read_length_prefixed_string(data, &mut pos)?;
+    let param_count = read_u32(data, &mut pos)? as usize;
+
+    let mut params = Vec::with_capacity(param_count);
+    for _ in 0..param_count {
+        let default = read_f32(data, &mut pos)?;
+        let min = read_f32(data, &mut pos)?;
+        let max = read_f32(data, &mut pos)?;
+        let name = read_length_prefixed_string(data, &mut pos)?;
+        params.push(ParamDescriptor { name, default, min, max });
+    }
+
+    Ok(ModuleDescriptor { name, version, params })
+}
+
+fn read_u32(data: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let bytes = data.get(*pos..*pos + 4).ok_or("truncated descriptor")?;
+    *pos += 4;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_f32(data: &[u8], pos: &mut usize) -> Result<f32, String> {
+    let bytes = data.get(*pos..*pos + 4).ok_or("truncated descriptor")?;
+    *pos += 4;
+    Ok(f32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_length_prefixed_string(data: &[u8], pos: &mut usize) -> Result<String, String> {
+    let len = read_u32(data, pos)? as usize;
+    let bytes = data.get(*pos..*pos + len).ok_or("truncated descriptor")?;
+    *pos += len;
+    String::from_utf8(bytes.to_vec()).map_err(|_| "descriptor string is not valid UTF-8".to_string())
+}
+
+/// Scan `dir` for `*.wasm` files and instantiate each one. A module that
+/// fails to load (bad bytes, missing export, trapping `init`) is logged and
+/// skipped rather than failing the whole scan - one broken user module
+/// shouldn't take down every other one.
+pub fn scan_and_load(engine: &Engine, dir: &Path) -> Vec<ModuleInstance> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().and_then(|e| e.to_str()) == Some("wasm"))
+        .filter_map(|entry| match ModuleInstance::load(engine, &entry.path()) {
+            Ok(instance) => Some(instance),
+            Err(err) => {
+                nih_plug::nih_log!("wasm_dsp: failed to load {:?}: {err}", entry.path());
+                None
+            }
+        })
+        .collect()
+}
+
+/// A lifecycle event delivered to the audio thread out of band from block
+/// processing, instead of polling for changes every block.
+pub enum ControlMessage {
+    /// Rescan `module_dir` and swap in the freshly built chain. Delivered
+    /// from the GUI thread to the dedicated watcher context; the actual
+    /// rescan/instantiate work happens off the audio thread (see
+    /// [`scan_and_load`]) before the result reaches `process()` via the
+    /// triple buffer.
+    Reload,
+    ResetModule(usize),
+    SetParam { module_index: usize, param_index: usize, value: f32 },
+}
+
+/// Non-realtime-producer, audio-thread-consumer queue for [`ControlMessage`]s.
+/// Pushes come from the GUI thread (an ordinary `Mutex` lock, never
+/// contended by the audio thread for long); draining is non-blocking so a
+/// rare lock collision just leaves the message for next block instead of
+/// stalling.
+#[derive(Default)]
+pub struct ControlQueue {
+    messages: Mutex<Vec<ControlMessage>>,
+}
+
+impl ControlQueue {
+    pub fn new() -> Self {
+        Self { messages: Mutex::new(Vec::new()) }
+    }
+
+    pub fn push(&self, message: ControlMessage) {
+        if let Ok(mut messages) = self.messages.lock() {
+            messages.push(message);
+        }
+    }
+
+    /// Drain and apply every pending message against `chain` - `Reload`
+    /// messages are handled by the caller (they need to rebuild the chain
+    /// off-thread), everything else is applied to the modules in place.
+    pub fn drain_into(&self, chain: &[ModuleInstance]) -> bool {
+        let Ok(mut messages) = self.messages.try_lock() else {
+            return false;
+        };
+        let mut reload_requested = false;
+        for message in messages.drain(..) {
+            match message {
+                ControlMessage::Reload => reload_requested = true,
+                ControlMessage::ResetModule(index) => {
+                    if let Some(module) = chain.get(index) {
+                        module.reset();
+                    }
+                }
+                ControlMessage::SetParam { module_index, param_index, value } => {
+                    if let Some(module) = chain.get(module_index) {
+                        module.set_param(param_index, value);
+                    }
+                }
+            }
+        }
+        reload_requested
+    }
+}
+
+/// Run every module in `chain` over `samples` in sequence, each one's
+/// output feeding the next - the plugin's extra user-supplied DSP stages.
+pub fn process_chain(chain: &[ModuleInstance], samples: &mut [f32], frame_count: usize, channel_count: usize) {
+    for module in chain {
+        module.process(samples, frame_count, channel_count);
+    }
+}
+
+/// Shared state the GUI and audio thread both need: the engine modules are
+/// compiled against, and the queue new lifecycle messages are pushed into.
+/// Lives on `Skipper` as `Arc`s alongside the `TripleBuffer` holding the
+/// active chain - see module docs for the swap protocol.
+pub struct WasmDspHost {
+    pub engine: Engine,
+    pub control: Arc<ControlQueue>,
+}
+
+impl WasmDspHost {
+    pub fn new() -> Self {
+        // Fuel metering is what makes this an actual sandbox rather than
+        // one in name only: without it, a guest module stuck in a loop
+        // inside `skipper_dsp_process` runs forever on the real-time audio
+        // thread, which is worse than a panic (there's no `catch_unwind`
+        // equivalent for "never returns"). Every call site below sets a
+        // fuel budget before calling into the guest.
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).expect("wasmtime Config::consume_fuel is always a valid config");
+
+        Self {
+            engine,
+            control: Arc::new(ControlQueue::new()),
+        }
+    }
+}
+
+/// Owns the background reload-watcher thread spawned by
+/// [`spawn_reload_watcher`] - same shape as `sync::SyncHandle`, and for the
+/// same reason: without this, every plugin instantiate/destroy cycle leaked
+/// the thread plus the `Engine`/`Arc`s it captured.
+pub struct ReloadWatcherHandle {
+    shutdown: Arc<std::sync::atomic::AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl ReloadWatcherHandle {
+    /// Signal the watcher to stop at its next 100ms poll tick and block
+    /// until it has actually exited.
+    pub fn shutdown(&mut self) {
+        self.shutdown.store(true, std::sync::atomic::Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ReloadWatcherHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Background reload watcher, spawned once from `initialize()` (see
+/// `sync::spawn` for the same persistent-background-thread shape). Holds
+/// the only copy of `module_dir` that ever gets rescanned - neither the GUI
+/// nor the audio thread touch the filesystem - and wakes on either a
+/// `ControlMessage::Reload` flag or its own slow poll interval, so a module
+/// dropped into the directory is picked up even without an explicit reload.
+pub fn spawn_reload_watcher(
+    engine: Engine,
+    module_dir: PathBuf,
+    reload_requested: Arc<std::sync::atomic::AtomicBool>,
+    chain_buf: Arc<crate::triple_buffer::TripleBuffer<Arc<Vec<ModuleInstance>>>>,
+) -> ReloadWatcherHandle {
+    let shutdown = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let thread_shutdown = shutdown.clone();
+
+    let thread = std::thread::spawn(move || loop {
+        if thread_shutdown.load(std::sync::atomic::Ordering::Acquire) {
+            return;
+        }
+        let chain = scan_and_load(&engine, &module_dir);
+        chain_buf.write(|slot| *slot = Arc::new(chain));
+        reload_requested.store(false, std::sync::atomic::Ordering::Release);
+
+        // Re-scan either when asked to, or periodically so a module copied
+        // into the directory without an explicit reload still gets picked up.
+        for _ in 0..50 {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            if reload_requested.load(std::sync::atomic::Ordering::Acquire)
+                || thread_shutdown.load(std::sync::atomic::Ordering::Acquire)
+            {
+                break;
+            }
+        }
+    });
+
+    ReloadWatcherHandle { shutdown, thread: Some(thread) }
+}
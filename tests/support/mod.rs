@@ -0,0 +1,349 @@
+//! Reusable CLAP host-harness test support: bundle discovery, ABI version
+//! gating, and a builder for the host extensions a test wants to expose -
+//! pulled out of `clap_track_info.rs`'s original hand-rolled one-offs so a
+//! second test file doesn't have to copy-paste the FFI dance.
+//!
+//! A test still owns its own extension *implementations* (the actual
+//! `clap_host_track_info`/etc. static vtables and their callbacks, since
+//! those carry test-specific assertions and state) - this module only owns
+//! finding the plugin, loading and version-checking its entry point, and
+//! wiring whichever vtables the test hands in through [`HostBuilder`] into
+//! a real `clap_host` the plugin can query.
+
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::ext::log::{clap_host_log, CLAP_EXT_LOG};
+use clap_sys::ext::params::{clap_host_params, CLAP_EXT_PARAMS};
+use clap_sys::ext::state::{clap_host_state, CLAP_EXT_STATE};
+use clap_sys::ext::track_info::{clap_host_track_info, CLAP_EXT_TRACK_INFO};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::version::CLAP_VERSION;
+use libloading::Library;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+/// One `*.clap` artifact found under `target/bundled`, with its on-disk
+/// loadable shared-library path already resolved for the current
+/// platform's bundle layout.
+pub struct DiscoveredPlugin {
+    /// The bundle name, e.g. "skipper" for "skipper.clap".
+    pub name: String,
+    pub library_path: PathBuf,
+}
+
+/// Scan `target/bundled` (relative to `CARGO_MANIFEST_DIR`) for `*.clap`
+/// artifacts and resolve each one's actual loadable library path. Returns
+/// an empty list rather than panicking if the directory doesn't exist yet -
+/// callers that need a specific plugin should use [`find_plugin`], which
+/// panics with a build-it-first message.
+pub fn discover_plugins() -> Vec<DiscoveredPlugin> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let bundled_dir = PathBuf::from(manifest_dir).join("target").join("bundled");
+
+    let Ok(entries) = std::fs::read_dir(&bundled_dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().and_then(|e| e.to_str()) == Some("clap"))
+        .filter_map(|entry| {
+            let bundle_path = entry.path();
+            let name = bundle_path.file_stem()?.to_string_lossy().into_owned();
+            let library_path = resolve_library_path(&bundle_path, &name);
+            Some(DiscoveredPlugin { name, library_path })
+        })
+        .collect()
+}
+
+/// Resolve a `.clap` bundle's actual loadable library: on macOS the bundle
+/// is a directory (`Contents/MacOS/<name>`); everywhere else the `.clap`
+/// path itself is already the flat shared object.
+fn resolve_library_path(bundle_path: &Path, name: &str) -> PathBuf {
+    if bundle_path.is_dir() {
+        bundle_path.join("Contents").join("MacOS").join(name)
+    } else {
+        bundle_path.to_path_buf()
+    }
+}
+
+/// Find a single discovered plugin by bundle name, panicking with a
+/// build-it-first message if it isn't there.
+pub fn find_plugin(name: &str) -> DiscoveredPlugin {
+    discover_plugins().into_iter().find(|p| p.name == name).unwrap_or_else(|| {
+        panic!("Plugin '{name}' not found under target/bundled. Run 'cargo xtask bundle {name} --release' first.")
+    })
+}
+
+/// This harness only checks the advertised major version against the
+/// `clap-sys` it was built with - CLAP's own compatibility rule (matching
+/// major version implies ABI compatibility once past 1.0), not a full
+/// feature-level negotiation.
+fn clap_version_compatible(advertised: clap_sys::version::clap_version) -> bool {
+    advertised.major == CLAP_VERSION.major
+}
+
+/// A loaded and initialized CLAP entry point. Its backing `Library` is kept
+/// alive for as long as this is, since any plugin instances created from it
+/// hold raw function pointers into the library's code.
+pub struct LoadedEntry {
+    _library: Library,
+    entry: *const clap_plugin_entry,
+}
+
+impl LoadedEntry {
+    /// Load `plugin.library_path`'s `clap_entry` symbol, reject it up front
+    /// if its advertised `clap_version` isn't compatible with this
+    /// harness's `clap-sys`, then run the entry's own `init()`.
+    pub fn load(plugin: &DiscoveredPlugin) -> Result<Self, String> {
+        let lib = unsafe { Library::new(&plugin.library_path) }
+            .map_err(|e| format!("failed to load {:?}: {e}", plugin.library_path))?;
+
+        let entry_symbol: libloading::Symbol<*const clap_plugin_entry> = unsafe { lib.get(b"clap_entry") }
+            .map_err(|e| format!("no clap_entry symbol in {:?}: {e}", plugin.library_path))?;
+        let entry_ptr = *entry_symbol;
+        let entry_ref = unsafe { &*entry_ptr };
+
+        if !clap_version_compatible(entry_ref.clap_version) {
+            return Err(format!(
+                "{} advertises CLAP {}.{}.{}, incompatible with this harness's clap-sys ({}.{}.{})",
+                plugin.name,
+                entry_ref.clap_version.major,
+                entry_ref.clap_version.minor,
+                entry_ref.clap_version.revision,
+                CLAP_VERSION.major,
+                CLAP_VERSION.minor,
+                CLAP_VERSION.revision,
+            ));
+        }
+
+        let path_cstr = CString::new(plugin.library_path.to_str().unwrap()).unwrap();
+        let init_ok = unsafe { (entry_ref.init.unwrap())(path_cstr.as_ptr()) };
+        if !init_ok {
+            return Err(format!("{} entry init() failed", plugin.name));
+        }
+
+        Ok(Self { _library: lib, entry: entry_ptr })
+    }
+
+    fn entry(&self) -> &clap_plugin_entry {
+        unsafe { &*self.entry }
+    }
+
+    fn factory(&self) -> &clap_plugin_factory {
+        let factory_ptr = unsafe { (self.entry().get_factory.unwrap())(CLAP_PLUGIN_FACTORY_ID.as_ptr()) };
+        assert!(!factory_ptr.is_null(), "failed to get plugin factory");
+        unsafe { &*(factory_ptr as *const clap_plugin_factory) }
+    }
+
+    /// The first plugin descriptor this entry's factory advertises - every
+    /// bundle in this repo contains exactly one.
+    pub fn first_descriptor(&self) -> &clap_plugin_descriptor {
+        let factory = self.factory();
+        let count = unsafe { (factory.get_plugin_count.unwrap())(factory) };
+        assert!(count > 0, "no plugins found in factory");
+        let descriptor = unsafe { (factory.get_plugin_descriptor.unwrap())(factory, 0) };
+        assert!(!descriptor.is_null(), "failed to get plugin descriptor");
+        unsafe { &*descriptor }
+    }
+
+    fn create_plugin(&self, host: *const clap_host, plugin_id: *const c_char) -> *const clap_plugin {
+        let factory = self.factory();
+        unsafe { (factory.create_plugin.unwrap())(factory, host, plugin_id) }
+    }
+}
+
+impl Drop for LoadedEntry {
+    fn drop(&mut self) {
+        unsafe { (self.entry().deinit.unwrap())() };
+    }
+}
+
+/// Host extension vtables a test may opt into exposing, keyed by the same
+/// names CLAP uses for them. A test owns the vtables themselves (so it can
+/// carry its own assertions/state in the callbacks) and only hands
+/// references in through [`HostBuilder`].
+#[derive(Default, Clone, Copy)]
+struct HostExtensions {
+    track_info: Option<&'static clap_host_track_info>,
+    log: Option<&'static clap_host_log>,
+    state: Option<&'static clap_host_state>,
+    params: Option<&'static clap_host_params>,
+}
+
+/// Backing storage for one mock host instance: the extensions it answers
+/// `get_extension` with, plus the identity strings CLAP requires as
+/// null-terminated C strings kept alive for the host's whole lifetime.
+struct HostData {
+    extensions: HostExtensions,
+    _name: CString,
+    _vendor: CString,
+    _url: CString,
+    _version: CString,
+}
+
+unsafe extern "C" fn host_get_extension(host: *const clap_host, extension_id: *const c_char) -> *const c_void {
+    if host.is_null() || extension_id.is_null() {
+        return ptr::null();
+    }
+    let host_ref = &*host;
+    if host_ref.host_data.is_null() {
+        return ptr::null();
+    }
+    let data = &*(host_ref.host_data as *const HostData);
+    let ext_id = CStr::from_ptr(extension_id);
+
+    if ext_id == CLAP_EXT_TRACK_INFO {
+        data.extensions.track_info.map_or(ptr::null(), |e| e as *const _ as *const c_void)
+    } else if ext_id == CLAP_EXT_LOG {
+        data.extensions.log.map_or(ptr::null(), |e| e as *const _ as *const c_void)
+    } else if ext_id == CLAP_EXT_STATE {
+        data.extensions.state.map_or(ptr::null(), |e| e as *const _ as *const c_void)
+    } else if ext_id == CLAP_EXT_PARAMS {
+        data.extensions.params.map_or(ptr::null(), |e| e as *const _ as *const c_void)
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn host_request_restart(_host: *const clap_host) {}
+unsafe extern "C" fn host_request_process(_host: *const clap_host) {}
+unsafe extern "C" fn host_request_callback(_host: *const clap_host) {}
+
+/// Builds a mock `clap_host` a test can register an arbitrary subset of
+/// extensions on before creating a plugin instance.
+pub struct HostBuilder {
+    extensions: HostExtensions,
+}
+
+impl HostBuilder {
+    pub fn new() -> Self {
+        Self { extensions: HostExtensions::default() }
+    }
+
+    pub fn with_track_info(mut self, ext: &'static clap_host_track_info) -> Self {
+        self.extensions.track_info = Some(ext);
+        self
+    }
+
+    pub fn with_log(mut self, ext: &'static clap_host_log) -> Self {
+        self.extensions.log = Some(ext);
+        self
+    }
+
+    pub fn with_state(mut self, ext: &'static clap_host_state) -> Self {
+        self.extensions.state = Some(ext);
+        self
+    }
+
+    pub fn with_params(mut self, ext: &'static clap_host_params) -> Self {
+        self.extensions.params = Some(ext);
+        self
+    }
+
+    pub fn build(self) -> MockHost {
+        let data = Box::new(HostData {
+            extensions: self.extensions,
+            _name: CString::new("Skipper Test Host").unwrap(),
+            _vendor: CString::new("Skipper Tests").unwrap(),
+            _url: CString::new("https://github.com/bedwards/skipper").unwrap(),
+            _version: CString::new("1.0.0").unwrap(),
+        });
+
+        let host = Box::new(clap_host {
+            clap_version: CLAP_VERSION,
+            host_data: &*data as *const HostData as *mut c_void,
+            name: data._name.as_ptr(),
+            vendor: data._vendor.as_ptr(),
+            url: data._url.as_ptr(),
+            version: data._version.as_ptr(),
+            get_extension: Some(host_get_extension),
+            request_restart: Some(host_request_restart),
+            request_process: Some(host_request_process),
+            request_callback: Some(host_request_callback),
+        });
+
+        MockHost { _data: data, host }
+    }
+}
+
+/// An owned mock `clap_host`, keeping its identity `CString`s and extension
+/// vtable pointers alive for as long as a plugin instance might query them.
+pub struct MockHost {
+    _data: Box<HostData>,
+    host: Box<clap_host>,
+}
+
+impl MockHost {
+    fn as_ptr(&self) -> *const clap_host {
+        &*self.host as *const clap_host
+    }
+}
+
+/// A created plugin instance, handling `init`/`activate`/`deactivate`/
+/// `destroy` ordering so a test can't forget a step or tear down out of
+/// order. Holds the `MockHost` and a reference to the `LoadedEntry` it came
+/// from so neither drops before the plugin itself is destroyed.
+pub struct PluginInstance<'a> {
+    plugin: *const clap_plugin,
+    _host: MockHost,
+    _entry: &'a LoadedEntry,
+    activated: bool,
+}
+
+impl<'a> PluginInstance<'a> {
+    /// Create a plugin instance from `entry`'s first descriptor, with
+    /// `host` as its mock host.
+    pub fn create(entry: &'a LoadedEntry, host: MockHost) -> Self {
+        let descriptor = entry.first_descriptor();
+        let plugin_ptr = entry.create_plugin(host.as_ptr(), descriptor.id);
+        assert!(!plugin_ptr.is_null(), "failed to create plugin instance");
+        Self { plugin: plugin_ptr, _host: host, _entry: entry, activated: false }
+    }
+
+    fn raw(&self) -> &clap_plugin {
+        unsafe { &*self.plugin }
+    }
+
+    /// Query host extensions (this is where the plugin calls
+    /// `clap_host::get_extension` for whatever it needs).
+    pub fn init(&mut self) -> bool {
+        unsafe { (self.raw().init.unwrap())(self.plugin) }
+    }
+
+    /// Activate the plugin - this is what calls the plugin's own
+    /// `Plugin::initialize()`.
+    pub fn activate(&mut self, sample_rate: f64, min_frames: u32, max_frames: u32) -> bool {
+        let ok = unsafe { (self.raw().activate.unwrap())(self.plugin, sample_rate, min_frames, max_frames) };
+        self.activated = ok;
+        ok
+    }
+
+    /// Query one of the plugin's own extensions (as opposed to
+    /// `HostBuilder`, which answers the plugin's queries of *host*
+    /// extensions) - e.g. `CLAP_EXT_STATE` to drive `clap_plugin_state`
+    /// directly. Null if the plugin doesn't implement `extension_id`.
+    pub fn get_extension(&self, extension_id: &CStr) -> *const c_void {
+        unsafe { (self.raw().get_extension.unwrap())(self.plugin, extension_id.as_ptr()) }
+    }
+
+    /// The raw `*const clap_plugin` a queried extension's own methods need
+    /// as their first argument (e.g. `clap_plugin_state::save`), since those
+    /// vtables are plugin methods, not host-side callbacks.
+    pub fn as_ptr(&self) -> *const clap_plugin {
+        self.plugin
+    }
+}
+
+impl Drop for PluginInstance<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.activated {
+                (self.raw().deactivate.unwrap())(self.plugin);
+            }
+            (self.raw().destroy.unwrap())(self.plugin);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Exercises the WASM DSP chain's guest ABI directly: `scan_and_load` plus
+//! `process_chain` against a hand-written gain module.
+//!
+//! A full round-trip through the CLAP activation path isn't reachable from a
+//! black-box host test the way `clap_track_info.rs` reaches `activate()` -
+//! `wasm_dsp.enabled` and `wasm_dsp.module_dir` live on `SharedState`, edited
+//! only from the GUI, with no host-automatable param or CLAP extension
+//! exposing them. So this test drives the module loader and processing
+//! entry points that `Skipper::process_impl` calls, rather than the plugin
+//! as a whole.
+
+use skipper::wasm_dsp;
+
+/// A trivial module that doubles every sample: exports `memory`, the four
+/// required `skipper_dsp_*` functions, and a `skipper_dsp_init` descriptor
+/// declaring itself as `gain 1.0` with no parameters.
+const GAIN_WAT: &str = r#"
+(module
+  (memory (export "memory") 2)
+  (global $bump (mut i32) (i32.const 1024))
+  (func (export "skipper_dsp_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $bump))
+    (global.set $bump (i32.add (global.get $bump) (local.get $len)))
+    (local.get $ptr))
+  (data (i32.const 0) "\04\00\00\00gain\03\00\00\001.0\00\00\00\00")
+  (func (export "skipper_dsp_init") (result i32)
+    (i32.const 0))
+  (func (export "skipper_dsp_process") (param $ptr i32) (param $frames i32) (param $channels i32)
+    (local $count i32)
+    (local $i i32)
+    (local.set $count (i32.mul (local.get $frames) (local.get $channels)))
+    (block $break
+      (loop $loop
+        (br_if $break (i32.ge_s (local.get $i) (local.get $count)))
+        (f32.store
+          (i32.add (local.get $ptr) (i32.mul (local.get $i) (i32.const 4)))
+          (f32.mul
+            (f32.load (i32.add (local.get $ptr) (i32.mul (local.get $i) (i32.const 4))))
+            (f32.const 2.0)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $loop))))
+  (func (export "skipper_dsp_set_param") (param $index i32) (param $value f32))
+  (func (export "skipper_dsp_reset")))
+"#;
+
+/// A module whose `skipper_dsp_process` never returns, proving the fuel
+/// budget `WasmDspHost::new`'s engine configures cuts it off instead of
+/// letting it hang whatever thread calls `process_chain`.
+const LOOP_WAT: &str = r#"
+(module
+  (memory (export "memory") 2)
+  (global $bump (mut i32) (i32.const 1024))
+  (func (export "skipper_dsp_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $bump))
+    (global.set $bump (i32.add (global.get $bump) (local.get $len)))
+    (local.get $ptr))
+  (data (i32.const 0) "\05\00\00\00loopy\03\00\00\001.0\00\00\00\00")
+  (func (export "skipper_dsp_init") (result i32)
+    (i32.const 0))
+  (func (export "skipper_dsp_process") (param $ptr i32) (param $frames i32) (param $channels i32)
+    (loop $forever
+      (br $forever)))
+  (func (export "skipper_dsp_set_param") (param $index i32) (param $value f32))
+  (func (export "skipper_dsp_reset")))
+"#;
+
+#[test]
+fn looping_module_is_cut_off_by_fuel_instead_of_hanging() {
+    let dir = std::env::temp_dir().join(format!("skipper_wasm_dsp_loop_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("failed to create scratch module dir");
+    std::fs::write(dir.join("loopy.wasm"), LOOP_WAT).expect("failed to write looping module");
+
+    // Use the same fuel-metered engine `Skipper` actually runs modules
+    // under, not a bare `Engine::default()` - that's the whole point of
+    // this test.
+    let engine = wasm_dsp::WasmDspHost::new().engine;
+    let chain = wasm_dsp::scan_and_load(&engine, &dir);
+    assert_eq!(chain.len(), 1, "expected the looping module to load");
+
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut samples = vec![1.0_f32, 2.0, 3.0, 4.0];
+        wasm_dsp::process_chain(&chain, &mut samples, 2, 2);
+        let _ = done_tx.send(samples);
+    });
+
+    let samples = done_rx
+        .recv_timeout(std::time::Duration::from_secs(10))
+        .expect("process_chain hung instead of being cut off by fuel exhaustion");
+    // The guest trapped on fuel exhaustion before writing anything back, so
+    // the block is left exactly as it went in - the module's output for
+    // this block is skipped rather than blocking forever.
+    assert_eq!(samples, vec![1.0, 2.0, 3.0, 4.0]);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn gain_module_scales_output_block() {
+    let dir = std::env::temp_dir().join(format!("skipper_wasm_dsp_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("failed to create scratch module dir");
+    std::fs::write(dir.join("gain.wasm"), GAIN_WAT).expect("failed to write gain module");
+
+    let engine = wasmtime::Engine::default();
+    let chain = wasm_dsp::scan_and_load(&engine, &dir);
+    assert_eq!(chain.len(), 1, "expected exactly one module to load");
+    assert_eq!(chain[0].descriptor.name, "gain");
+    assert_eq!(chain[0].descriptor.version, "1.0");
+    assert!(chain[0].descriptor.params.is_empty());
+
+    let mut samples = vec![0.5_f32, -0.25, 1.0, 0.0];
+    wasm_dsp::process_chain(&chain, &mut samples, 2, 2);
+    assert_eq!(samples, vec![1.0, -0.5, 2.0, 0.0]);
+
+    std::fs::remove_dir_all(&dir).ok();
+}